@@ -1,19 +1,29 @@
-use std::{path::PathBuf, sync::Arc, time::Instant};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::Context;
 
 use crate::{
     Engine, FixtureCollection, Project,
+    dmx::UniverseId,
     event::Event,
     gdtf::attr::AttributeName,
     object::{
         Executor, ExecutorButton, ExecutorButtonAction, ExecutorContent, ExecutorId, Object,
         ObjectId, ObjectKind, Preset, PresetContent, PresetId, PresetKind, Slot,
     },
+    output::{OutputInstanceDefinition, OutputInstanceKind},
     patch::FixtureId,
-    value::{AttributeValue, AttributeValues},
+    trigger::{TriggerTarget, TriggersDefinition},
+    value::{AttributeValue, AttributeValues, ClampedValue},
 };
 
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
 pub enum Command {
     Activate { object_kind: ObjectKind, object_id: ObjectId },
     // FIXME: Convert these to FixtureCollections.
@@ -30,15 +40,34 @@ pub enum Command {
     ExecutorToggleEnabled { executor_id: ExecutorId },
     ExecutorSetEnabled { executor_id: ExecutorId, value: bool },
     ExecutorButton { executor_id: ExecutorId, button: ExecutorButton, pressed: bool },
+    ExecutorPageSelect { object_id: ObjectId },
+    ExecutorCueGo { executor_id: ExecutorId, cue_index: Option<usize> },
+    ExecutorCueLoad { executor_id: ExecutorId, cue_index: usize },
 
     ProgrammerSet { fixtures: FixtureCollection, attribute: AttributeName, value: AttributeValue },
+    ProgrammerAdd { attribute: AttributeName, delta: f32, from: ClampedValue, to: ClampedValue },
     ProgrammerActivate { fixtures: FixtureCollection, attribute: AttributeName },
     ProgrammerClear,
+    ProgrammerCaptureSacn { universe_id: UniverseId, duration_ms: u32 },
+    ProgrammerCaptureCancel,
 
     Store { kind: StoreKind },
     Rename { object_id: ObjectId, object_kind: ObjectKind, name: String },
 
     EncoderSetValue { encoder_ix: usize, value: f32 },
+    EncoderAddValue { encoder_ix: usize, delta: f32 },
+
+    TriggersSet { definition: TriggersDefinition },
+    MidiLearnStart { target: TriggerTarget },
+    MidiLearnCancel,
+
+    OutputInstanceAdd { definition: OutputInstanceDefinition },
+    OutputInstanceEdit { index: usize, definition: OutputInstanceDefinition },
+    OutputInstanceRemove { kind: OutputInstanceKind, index: usize },
+    OutputRecordStart { path: PathBuf },
+    OutputRecordStop,
+    OutputPlaybackStart { path: PathBuf },
+    OutputPlaybackStop,
 
     Save { path: PathBuf },
 }
@@ -53,7 +82,9 @@ impl Command {
                     Command::SelectionAdd { fixture_ids }.execute(engine)?;
                 }
                 ObjectKind::Sequence => {}
-                ObjectKind::ExecutorPage => {}
+                ObjectKind::ExecutorPage => {
+                    Command::ExecutorPageSelect { object_id }.execute(engine)?;
+                }
                 ObjectKind::LayoutPage => {}
                 ObjectKind::Preset(preset_kind) => {
                     let preset = engine
@@ -213,11 +244,12 @@ impl Command {
                 let page = objects.executor_pages.get_by_object_id_mut(&executor_id.page)?;
                 let executor = page.executor_mut(executor_id.slot)?;
 
-                let action = match executor.content() {
+                let was_enabled = executor.enabled();
+                let (action, previous_cue_index) = match executor.content() {
                     Some(ExecutorContent::Sequence(sc)) => match button {
-                        ExecutorButton::Button1 => sc.button1(),
-                        ExecutorButton::Button2 => sc.button2(),
-                        ExecutorButton::Button3 => sc.button3(),
+                        ExecutorButton::Button1 => (sc.button1(), sc.cue_index()),
+                        ExecutorButton::Button2 => (sc.button2(), sc.cue_index()),
+                        ExecutorButton::Button3 => (sc.button3(), sc.cue_index()),
                     },
                     None => return Ok(()),
                 };
@@ -255,7 +287,9 @@ impl Command {
                         if pressed {
                             let sequence_obj =
                                 objects.sequences.get_by_object_id(&sc.sequence())?;
-                            if sc.cue_index() + 1 < sequence_obj.cues().len() {
+                            if let Some(cue_index) = sc.loaded_cue_index.take() {
+                                sc.cue_index = cue_index;
+                            } else if sc.cue_index() + 1 < sequence_obj.cues().len() {
                                 sc.cue_index += 1;
                             }
                         }
@@ -266,21 +300,93 @@ impl Command {
                         };
 
                         if pressed {
+                            sc.loaded_cue_index = None;
                             sc.cue_index = sc.cue_index().saturating_sub(1);
                         }
                     }
                 }
 
-                match &mut executor.content {
-                    Some(ExecutorContent::Sequence(sc)) => sc.last_activation_time = Instant::now(),
-                    None => {}
+                let fired_cue_index = match &mut executor.content {
+                    Some(ExecutorContent::Sequence(sc)) => {
+                        sc.last_activation_time = Instant::now();
+                        let is_cue_go = matches!(
+                            action,
+                            ExecutorButtonAction::CueGoNext | ExecutorButtonAction::CueGoPrevious
+                        );
+                        let fired = !was_enabled || sc.cue_index() != previous_cue_index;
+                        (is_cue_go && pressed && fired).then_some(sc.cue_index())
+                    }
+                    None => None,
+                };
+
+                let object_id = page.id();
+                engine.emit(Event::ObjectChanged {
+                    object_kind: ObjectKind::ExecutorPage,
+                    object_id,
+                });
+                if let Some(cue_index) = fired_cue_index {
+                    engine.emit(Event::CueFired {
+                        executor_id,
+                        cue_index,
+                        from_msc: engine.handling_msc,
+                    });
                 }
+            }
+            Command::ExecutorCueGo { executor_id, cue_index } => {
+                let objects = Arc::make_mut(&mut engine.objects);
+                let page = objects.executor_pages.get_by_object_id_mut(&executor_id.page)?;
+                let executor = page.executor_mut(executor_id.slot)?;
+                let was_enabled = executor.enabled();
+
+                let Some(ExecutorContent::Sequence(sc)) = &mut executor.content else {
+                    return Ok(());
+                };
+
+                let cue_count = objects.sequences.get_by_object_id(&sc.sequence())?.cues().len();
+                let cue_index = match cue_index.or_else(|| sc.loaded_cue_index.take()) {
+                    Some(cue_index) => {
+                        anyhow::ensure!(cue_index < cue_count, "no cue at index {cue_index}");
+                        cue_index
+                    }
+                    None if was_enabled => (sc.cue_index() + 1).min(cue_count.saturating_sub(1)),
+                    None => sc.cue_index(),
+                };
+                // A GO on the last cue of a running sequence doesn't fire anything.
+                let fired = !was_enabled || cue_index != sc.cue_index();
+                sc.cue_index = cue_index;
+                sc.last_activation_time = Instant::now();
+                executor.set_enabled(true);
 
                 let object_id = page.id();
                 engine.emit(Event::ObjectChanged {
                     object_kind: ObjectKind::ExecutorPage,
                     object_id,
                 });
+                if fired {
+                    engine.emit(Event::CueFired {
+                        executor_id,
+                        cue_index,
+                        from_msc: engine.handling_msc,
+                    });
+                }
+            }
+            Command::ExecutorCueLoad { executor_id, cue_index } => {
+                let objects = Arc::make_mut(&mut engine.objects);
+                let page = objects.executor_pages.get_by_object_id_mut(&executor_id.page)?;
+                let executor = page.executor_mut(executor_id.slot)?;
+
+                let Some(ExecutorContent::Sequence(sc)) = &mut executor.content else {
+                    return Ok(());
+                };
+
+                let cue_count = objects.sequences.get_by_object_id(&sc.sequence())?.cues().len();
+                anyhow::ensure!(cue_index < cue_count, "no cue at index {cue_index}");
+                sc.loaded_cue_index = Some(cue_index);
+            }
+            Command::ExecutorPageSelect { object_id } => {
+                engine.objects().executor_pages().get_by_object_id(&object_id)?;
+                engine.selected_executor_page = Some(object_id);
+                engine.emit(Event::ExecutorPageSelected { object_id });
             }
 
             Command::ProgrammerSet { fixtures, attribute, value } => {
@@ -295,6 +401,25 @@ impl Command {
                 }
                 engine.emit(Event::ProgrammerChanged);
             }
+            Command::ProgrammerAdd { attribute, delta, from, to } => {
+                // Adds to the values of the selected fixtures, keeping them between `from`
+                // and `to`. Values that aren't in the programmer yet start at `from`.
+                let (min, max) = if to < from { (to, from) } else { (from, to) };
+                let programmer = Arc::make_mut(&mut engine.programmer);
+                for fixture_id in engine.selection.fixture_ids() {
+                    let current = match programmer.values().get(fixture_id, &attribute) {
+                        Some(AttributeValue::Clamped(current)) => current,
+                        _ => from,
+                    };
+                    let value = (current.as_f32() + delta).clamp(min.as_f32(), max.as_f32());
+                    programmer.set(
+                        *fixture_id,
+                        attribute.clone(),
+                        AttributeValue::Clamped(ClampedValue::new(value)),
+                    );
+                }
+                engine.emit(Event::ProgrammerChanged);
+            }
             Command::ProgrammerActivate { fixtures, attribute } => {
                 let fixture_ids = fixtures
                     .fixture_ids(engine.objects(), engine.patch())?
@@ -318,6 +443,13 @@ impl Command {
                 programmer.clear();
                 engine.emit(Event::ProgrammerChanged);
             }
+            Command::ProgrammerCaptureSacn { universe_id, duration_ms } => {
+                let duration = Duration::from_millis(duration_ms.into());
+                engine.input_agent.start_sacn_capture(universe_id, duration)?;
+            }
+            Command::ProgrammerCaptureCancel => {
+                engine.input_agent.cancel_sacn_capture();
+            }
 
             Command::Store { kind: StoreKind::Preset { slot, kind } } => {
                 let programmer_values = engine.programmer().values();
@@ -406,9 +538,62 @@ impl Command {
             }
 
             Command::EncoderSetValue { encoder_ix, value } => {
+                engine.encoder_values.insert(encoder_ix, value);
                 engine.emit(Event::EncoderChanged { encoder_ix, value });
             }
 
+            Command::EncoderAddValue { encoder_ix, delta } => {
+                let current = engine.encoder_values.get(&encoder_ix).copied().unwrap_or_default();
+                let value = (current + delta).clamp(0.0, 1.0);
+                Command::EncoderSetValue { encoder_ix, value }.execute(engine)?;
+            }
+
+            Command::TriggersSet { definition } => {
+                engine.triggers_agent.set_definition(definition)?;
+            }
+
+            Command::MidiLearnStart { target } => {
+                engine.triggers_agent.start_midi_learn(target)?;
+            }
+
+            Command::MidiLearnCancel => {
+                engine.triggers_agent.cancel_midi_learn();
+            }
+
+            Command::OutputInstanceAdd { definition } => {
+                engine.output_agent.add_instance(definition)?;
+                engine.update_own_sacn_cids();
+                engine.emit(Event::OutputChanged);
+            }
+
+            Command::OutputInstanceEdit { index, definition } => {
+                engine.output_agent.edit_instance(index, definition)?;
+                engine.update_own_sacn_cids();
+                engine.emit(Event::OutputChanged);
+            }
+
+            Command::OutputInstanceRemove { kind, index } => {
+                engine.output_agent.remove_instance(kind, index)?;
+                engine.update_own_sacn_cids();
+                engine.emit(Event::OutputChanged);
+            }
+
+            Command::OutputRecordStart { path } => {
+                engine.output_agent.start_recording(&path)?;
+            }
+
+            Command::OutputRecordStop => {
+                engine.output_agent.stop_recording()?;
+            }
+
+            Command::OutputPlaybackStart { path } => {
+                engine.output_agent.start_playback(&path)?;
+            }
+
+            Command::OutputPlaybackStop => {
+                engine.output_agent.stop_playback();
+            }
+
             Command::Save { path } => {
                 let project = Project::load_from_engine(path.clone(), engine);
                 project.save_to_folder()?;
@@ -434,6 +619,9 @@ fn reset_sequence_to_start_if_disabled(executor: &mut Executor) {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
 pub enum StoreKind {
     Preset { slot: Slot, kind: PresetKind },
 }
@@ -513,6 +513,12 @@ impl str::FromStr for UniverseId {
     }
 }
 
+/// The maximum number of slots in a DMX universe, excluding the start code.
+pub const MAX_UNIVERSE_SIZE: usize = 512;
+
+/// The start code for regular DMX data.
+pub const NULL_START_CODE: u8 = 0x00;
+
 /// A DMX universe that contains 512 [`Value`]s.
 ///
 /// The universe has:
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
     thread,
@@ -9,16 +10,19 @@ use arc_swap::ArcSwap;
 use flume::{Receiver, Sender};
 
 use crate::{
-    Project,
+    FixtureCollection, Project,
     cmd::Command,
+    dmx::{self, Multiverse, UniverseId},
     event::{Event, EventListener},
-    object::Objects,
-    output::OutputAgent,
+    input::{InputAgent, SacnNetworkSource},
+    object::{ObjectId, Objects},
+    output::{OutputAgent, OutputInstanceStatus},
     patch::Patch,
     pipeline::Pipeline,
     programmer::Programmer,
     selection::Selection,
-    trigger::{Trigger, TriggersAgent},
+    trigger::{MidiDeviceStatus, Trigger, TriggersAgent, msc},
+    value::{AttributeValue, ClampedValue},
 };
 
 pub struct Engine {
@@ -30,12 +34,18 @@ pub struct Engine {
     pub(crate) pipeline: Arc<Pipeline>,
     pub(crate) selection: Arc<Selection>,
     pub(crate) highlight: bool,
+    pub(crate) selected_executor_page: Option<ObjectId>,
+    /// Set while commands received over MSC are executed, so they aren't echoed back.
+    pub(crate) handling_msc: bool,
+    pub(crate) encoder_values: HashMap<usize, f32>,
 
     pub(crate) triggers_agent: TriggersAgent,
     pub(crate) output_agent: OutputAgent,
+    pub(crate) input_agent: InputAgent,
 
     event_tx: flume::Sender<Event>,
     event_listener: EventListener,
+    event_subscribers: Vec<flume::Sender<Event>>,
     event_buffer: Vec<Event>,
 }
 
@@ -48,10 +58,13 @@ impl Engine {
         let (event_tx, event_rx) = flume::unbounded();
         let event_listener = EventListener::new(event_rx);
 
-        let output_agent = OutputAgent::new(project.output().clone())?;
+        let mut output_agent = OutputAgent::new(project.output().clone())?;
+        output_agent.set_patched_addresses(pipeline.cache().patched_addresses().copied().collect());
+        let input_agent = InputAgent::new(project.input().clone());
+        output_agent.set_input_multiverse(input_agent.shared_multiverse());
         let triggers_agent = TriggersAgent::new(project.triggers().clone())?;
 
-        let engine = Self {
+        let mut engine = Self {
             showfile_path: project.path().map(|p| p.to_path_buf()),
 
             patch: Arc::new(patch),
@@ -60,14 +73,20 @@ impl Engine {
             programmer: Arc::new(Programmer::new()),
             pipeline: Arc::new(pipeline),
             highlight: false,
+            selected_executor_page: None,
+            handling_msc: false,
+            encoder_values: HashMap::new(),
 
             event_tx,
             event_listener,
+            event_subscribers: Vec::new(),
             event_buffer: Vec::new(),
 
             output_agent,
+            input_agent,
             triggers_agent,
         };
+        engine.update_own_sacn_cids();
 
         Ok(engine)
     }
@@ -88,6 +107,10 @@ impl Engine {
         &self.output_agent
     }
 
+    pub fn input_agent(&self) -> &InputAgent {
+        &self.input_agent
+    }
+
     pub fn objects(&self) -> &Objects {
         &self.objects
     }
@@ -108,6 +131,10 @@ impl Engine {
         self.highlight
     }
 
+    pub fn selected_executor_page(&self) -> Option<ObjectId> {
+        self.selected_executor_page
+    }
+
     pub fn event_listener(&self) -> EventListener {
         self.event_listener.clone()
     }
@@ -125,9 +152,19 @@ impl Engine {
             pipeline: Arc::clone(&self.pipeline),
             selection: Arc::clone(&self.selection),
             highlight: self.highlight,
+            selected_executor_page: self.selected_executor_page,
+            midi_devices: self.triggers_agent.midi_devices(),
+            sacn_network_sources: self.input_agent.sacn_network_sources(),
+            output_statuses: self.output_agent.statuses(),
         }
     }
 
+    /// Keeps the sACN input from receiving what the sACN output instances send.
+    pub(crate) fn update_own_sacn_cids(&mut self) {
+        let cids = self.output_agent.definition().sacn().instances().iter().map(|i| i.cid());
+        self.input_agent.set_own_sacn_cids(cids.collect());
+    }
+
     pub(crate) fn emit(&mut self, event: Event) {
         self.event_buffer.push(event);
     }
@@ -137,6 +174,7 @@ impl Engine {
 
         const INTERVAL: Duration = Duration::new(0, ((1_000_000_000_f64 / 60.0).round()) as u32);
 
+        self.input_agent.start();
         self.output_agent.start();
 
         let mut next_tick = Instant::now() + INTERVAL;
@@ -186,6 +224,7 @@ impl Engine {
         }
 
         self.output_agent.stop();
+        self.input_agent.stop();
 
         log::info!("Stopped Radiant Engine");
     }
@@ -207,6 +246,12 @@ impl Engine {
                 }
                 true
             }
+            EngineMessage::Subscribe { resp } => {
+                let (event_tx, event_rx) = flume::unbounded();
+                self.event_subscribers.push(event_tx);
+                let _ = resp.send(EventListener::new(event_rx));
+                true
+            }
             EngineMessage::Shutdown { resp } => {
                 if let Some(resp) = resp {
                     let _ = resp.send(());
@@ -219,25 +264,46 @@ impl Engine {
     fn tick(&mut self, snapshot_store: &Arc<ArcSwap<EngineSnapshot>>) {
         let mut snapshot_dirty = false;
         for trigger in self.triggers_agent.drain() {
-            match trigger {
-                Trigger::ExecutorMaster { executor_id, value } => {
-                    self.execute(Command::ExecutorSetMaster { executor_id, value })
-                        .map_err(|err| log::error!("{err}"))
-                        .ok();
-                    snapshot_dirty = true;
-                }
-                Trigger::ExecutorButton { executor_id, button, pressed } => {
-                    self.execute(Command::ExecutorButton { executor_id, button, pressed })
-                        .map_err(|err| log::error!("{err}"))
-                        .ok();
-                    snapshot_dirty = true;
-                }
-                Trigger::EncoderSetValue { encoder_ix, value } => {
-                    self.execute(Command::EncoderSetValue { encoder_ix, value })
-                        .map_err(|err| log::error!("{err}"))
-                        .ok();
+            snapshot_dirty |= self.handle_trigger(trigger);
+        }
+
+        if self.triggers_agent.poll_midi_devices() {
+            self.emit(Event::MidiDevicesChanged);
+            snapshot_dirty = true;
+        }
+
+        if let Some(changed) = self.output_agent.poll_statuses() {
+            for status in changed {
+                self.emit(Event::OutputStateChanged {
+                    kind: status.kind,
+                    index: status.index,
+                    state: status.state,
+                });
+            }
+            snapshot_dirty = true;
+        }
+
+        if self.input_agent.poll_sacn_network_sources() {
+            self.emit(Event::SacnNetworkSourcesChanged);
+            snapshot_dirty = true;
+        }
+
+        match self.input_agent.poll_sacn_capture() {
+            Some(Ok((universe_id, universe))) => {
+                self.load_captured_universe(universe_id, universe);
+                snapshot_dirty = true;
+            }
+            Some(Err(err)) => log::error!("Failed to capture sACN: {err}"),
+            None => {}
+        }
+
+        match self.triggers_agent.poll_midi_learn() {
+            Ok(definitions) => {
+                for definition in definitions {
+                    self.emit(Event::MidiTriggerLearned { definition });
                 }
             }
+            Err(err) => log::error!("Failed to learn MIDI trigger: {err}"),
         }
 
         self.resolve_pipeline();
@@ -247,10 +313,101 @@ impl Engine {
         }
 
         for event in self.event_buffer.drain(..) {
+            self.triggers_agent.handle_event(&event, &self.objects, self.selected_executor_page);
+            self.event_subscribers.retain(|tx| tx.send(event.clone()).is_ok());
             let _ = self.event_tx.send(event);
         }
 
-        self.output_agent.update(self.pipeline.multiverse().clone());
+        let mut multiverse = self.pipeline.multiverse().clone();
+        self.input_agent.merge_into_output(&mut multiverse);
+        self.output_agent.update(multiverse);
+    }
+
+    /// Handles a trigger from a control surface, returning whether the snapshot changed.
+    fn handle_trigger(&mut self, trigger: Trigger) -> bool {
+        match trigger {
+            // Control surfaces follow the selected executor page.
+            Trigger::ExecutorMaster { executor_id, value, relative } => {
+                let executor_id = executor_id.on_selected_page(self.selected_executor_page);
+                let value = if relative {
+                    let current = self.objects.executor(&executor_id).map(|e| e.master());
+                    (current.unwrap_or_default() + value).clamp(0.0, 1.0)
+                } else {
+                    value
+                };
+                self.execute(Command::ExecutorSetMaster { executor_id, value })
+                    .map_err(|err| log::error!("{err}"))
+                    .ok();
+                true
+            }
+            Trigger::ExecutorButton { executor_id, button, pressed } => {
+                let executor_id = executor_id.on_selected_page(self.selected_executor_page);
+                self.execute(Command::ExecutorButton { executor_id, button, pressed })
+                    .map_err(|err| log::error!("{err}"))
+                    .ok();
+                true
+            }
+            Trigger::EncoderSetValue { encoder_ix, value, relative } => {
+                let command = if relative {
+                    Command::EncoderAddValue { encoder_ix, delta: value }
+                } else {
+                    Command::EncoderSetValue { encoder_ix, value }
+                };
+                self.execute(command).map_err(|err| log::error!("{err}")).ok();
+                false
+            }
+            Trigger::ProgrammerAttribute { attribute, from, to, value, relative } => {
+                let range = to.as_f32() - from.as_f32();
+                let command = if relative {
+                    Command::ProgrammerAdd { attribute, delta: value * range, from, to }
+                } else {
+                    let fixtures =
+                        FixtureCollection::Multiple(self.selection.fixture_ids().to_vec());
+                    let value = ClampedValue::new(from.as_f32() + value * range);
+                    Command::ProgrammerSet {
+                        fixtures,
+                        attribute,
+                        value: AttributeValue::Clamped(value),
+                    }
+                };
+                self.execute(command).map_err(|err| log::error!("{err}")).ok();
+                true
+            }
+            Trigger::Command(command) => {
+                self.execute(command).map_err(|err| log::error!("{err}")).ok();
+                true
+            }
+            Trigger::Msc(command) => {
+                let commands =
+                    msc::playback_commands(&command, &self.objects, self.selected_executor_page);
+                match commands {
+                    Ok(commands) => {
+                        self.handling_msc = true;
+                        for command in commands {
+                            self.execute(command).map_err(|err| log::error!("{err}")).ok();
+                        }
+                        self.handling_msc = false;
+                    }
+                    Err(err) => log::warn!("Failed to handle MSC command {command:?}: {err}"),
+                }
+                true
+            }
+        }
+    }
+
+    /// Decodes a universe captured from another console into the programmer.
+    fn load_captured_universe(&mut self, universe_id: UniverseId, universe: dmx::Universe) {
+        let mut multiverse = Multiverse::new();
+        multiverse.create_universe(universe_id, universe);
+        let values = self.pipeline.unmap(&multiverse);
+
+        let programmer = Arc::make_mut(&mut self.programmer);
+        for (fixture_id, attribute, value) in values.values() {
+            programmer.set(*fixture_id, attribute.clone(), *value);
+        }
+
+        log::info!("Captured sACN universe {universe_id} into the programmer");
+        self.emit(Event::ProgrammerChanged);
     }
 
     fn resolve_pipeline(&mut self) {
@@ -276,6 +433,7 @@ impl Engine {
 
 enum EngineMessage {
     Command { command: Command, resp: Option<Sender<anyhow::Result<()>>> },
+    Subscribe { resp: Sender<EventListener> },
     Shutdown { resp: Option<Sender<()>> },
 }
 
@@ -332,6 +490,15 @@ impl EngineHandle {
         self.inner.event_listener.clone()
     }
 
+    pub fn subscribe(&self) -> anyhow::Result<EventListener> {
+        let (resp_tx, resp_rx) = flume::bounded(1);
+        self.inner
+            .tx
+            .send(EngineMessage::Subscribe { resp: resp_tx })
+            .map_err(|_| anyhow::anyhow!("Engine thread stopped"))?;
+        resp_rx.recv().map_err(|_| anyhow::anyhow!("Engine thread stopped"))
+    }
+
     pub fn shutdown(&self) -> anyhow::Result<()> {
         let (resp_tx, resp_rx) = flume::bounded(1);
         self.inner
@@ -358,6 +525,10 @@ pub struct EngineSnapshot {
     pipeline: Arc<Pipeline>,
     selection: Arc<Selection>,
     highlight: bool,
+    selected_executor_page: Option<ObjectId>,
+    midi_devices: Arc<Vec<MidiDeviceStatus>>,
+    sacn_network_sources: Arc<Vec<SacnNetworkSource>>,
+    output_statuses: Arc<Vec<OutputInstanceStatus>>,
 }
 
 impl EngineSnapshot {
@@ -388,4 +559,245 @@ impl EngineSnapshot {
     pub fn highlight(&self) -> bool {
         self.highlight
     }
+
+    pub fn selected_executor_page(&self) -> Option<ObjectId> {
+        self.selected_executor_page
+    }
+
+    pub fn midi_devices(&self) -> Arc<Vec<MidiDeviceStatus>> {
+        Arc::clone(&self.midi_devices)
+    }
+
+    /// The sACN sources on the network, to spot other consoles sending the same universes.
+    pub fn sacn_network_sources(&self) -> Arc<Vec<SacnNetworkSource>> {
+        Arc::clone(&self.sacn_network_sources)
+    }
+
+    /// The health of every output instance, to warn when a node or widget drops out.
+    pub fn output_statuses(&self) -> Arc<Vec<OutputInstanceStatus>> {
+        Arc::clone(&self.output_statuses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+    use crate::dmx::{Address, Channel, Value};
+    use crate::gdtf::attr::AttributeName;
+    use crate::input::InputDefinition;
+    use crate::object::{ExecutorButton, ExecutorId, Object, Slot};
+    use crate::output::{
+        LoopbackOutputInstanceDefinition, OutputDefinition, OutputInstanceDefinition,
+    };
+    use crate::patch::{FixtureId, FixtureIdPart};
+    use crate::trigger::TriggersDefinition;
+    use crate::trigger::msc::{MscCommand, MscCue};
+
+    /// The example project, without its outputs, inputs and triggers, so no sockets or
+    /// devices are opened when the engine starts.
+    fn example_engine() -> Engine {
+        let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples/capital_inspired");
+        let mut project = Project::load_from_folder(example).unwrap();
+        project.set_output(OutputDefinition::default());
+        project.set_input(InputDefinition::default());
+        project.set_triggers(TriggersDefinition::default());
+        Engine::new(project).unwrap()
+    }
+
+    fn drain_fired_cues(engine: &mut Engine) -> Vec<Event> {
+        engine
+            .event_buffer
+            .drain(..)
+            .filter(|event| matches!(event, Event::CueFired { .. }))
+            .collect()
+    }
+
+    /// The executor of the 'Colors' sequence, which has 4 cues.
+    fn colors_executor(engine: &Engine) -> ExecutorId {
+        let cue = MscCue::new("1", Some("11".to_string()));
+        let commands =
+            msc::playback_commands(&MscCommand::Go { cue: Some(cue) }, &engine.objects, None);
+        let [Command::ExecutorCueGo { executor_id, .. }] = commands.unwrap()[..] else {
+            panic!("expected a single executor for the 'Colors' sequence");
+        };
+        executor_id
+    }
+
+    /// Receives frames until one has `value` at `address`.
+    fn wait_for_value(frames: &Receiver<Multiverse>, address: &Address, value: Value) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while let Ok(frame) = frames.recv_deadline(deadline) {
+            if frame.value(address) == Some(value) {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn fire_executor_into_loopback_output() {
+        let mut engine = example_engine();
+        engine
+            .execute(Command::OutputInstanceAdd {
+                definition: OutputInstanceDefinition::Loopback(LoopbackOutputInstanceDefinition {
+                    name: "test".to_string(),
+                    universe_ids: vec![UniverseId::MIN],
+                    refresh_rate: 100.0,
+                }),
+            })
+            .unwrap();
+        let frames = engine.output_agent().loopback_instances()[0].subscribe();
+        let page = engine.objects().executor_pages().all()[0].id();
+        let handle = EngineHandle::new(engine);
+
+        // The dimmer of 'Wash 1', driven by the 'WASH Intensity' sequence on executor 2.
+        let dimmer = Address::new(UniverseId::MIN, Channel::new(153).unwrap());
+        assert!(wait_for_value(&frames, &dimmer, Value(0)));
+
+        handle
+            .execute(Command::ExecutorSetMaster {
+                executor_id: ExecutorId::new(page, Slot::new(NonZeroU32::new(2).unwrap())),
+                value: 1.0,
+            })
+            .unwrap();
+        assert!(wait_for_value(&frames, &dimmer, Value(255)));
+
+        handle.shutdown().unwrap();
+    }
+
+    #[test]
+    fn cues_fired_over_msc_are_not_echoed() {
+        let mut engine = example_engine();
+        let definition = serde_json::from_value(serde_json::json!({
+            "midi": [],
+            "msc": { "device_name": "MSC", "device_id": 1, "transmit_device_id": 2 },
+        }))
+        .unwrap();
+        engine.triggers_agent.set_definition(definition).unwrap();
+
+        // Cue 3 of the 'Colors' sequence.
+        let cue = MscCue::new("3", Some("11".to_string()));
+        engine.handle_trigger(Trigger::Msc(MscCommand::Go { cue: Some(cue) }));
+        let fired = drain_fired_cues(&mut engine);
+        let [event @ Event::CueFired { executor_id, from_msc: true, .. }] = fired.as_slice() else {
+            panic!("expected a single cue fired over MSC, got {fired:?}");
+        };
+        assert_eq!(engine.triggers_agent.msc_go_message(event, &engine.objects), None);
+
+        let executor_id = *executor_id;
+        engine.execute(Command::ExecutorCueGo { executor_id, cue_index: Some(0) }).unwrap();
+        let fired = drain_fired_cues(&mut engine);
+        assert!(engine.triggers_agent.msc_go_message(&fired[0], &engine.objects).is_some());
+    }
+
+    #[test]
+    fn cue_go_fires_only_when_the_cue_changes() {
+        let mut engine = example_engine();
+        let executor_id = colors_executor(&engine);
+
+        engine.execute(Command::ExecutorCueGo { executor_id, cue_index: Some(3) }).unwrap();
+        assert_eq!(drain_fired_cues(&mut engine).len(), 1);
+
+        // Already on the last cue.
+        engine.execute(Command::ExecutorCueGo { executor_id, cue_index: None }).unwrap();
+        let button = ExecutorButton::Button2;
+        engine.execute(Command::ExecutorButton { executor_id, button, pressed: true }).unwrap();
+        assert!(drain_fired_cues(&mut engine).is_empty());
+
+        // Going to a specific cue keeps the loaded cue for the next GO.
+        engine.execute(Command::ExecutorCueLoad { executor_id, cue_index: 1 }).unwrap();
+        engine.execute(Command::ExecutorCueGo { executor_id, cue_index: Some(0) }).unwrap();
+        engine.execute(Command::ExecutorCueGo { executor_id, cue_index: None }).unwrap();
+        let fired = drain_fired_cues(&mut engine);
+        assert!(matches!(fired[..], [_, Event::CueFired { cue_index: 1, .. }]));
+    }
+
+    #[test]
+    fn executor_triggers_follow_the_selected_page() {
+        let mut engine = example_engine();
+        let page = engine.objects().executor_pages().all()[0].id();
+        let slot = Slot::new(NonZeroU32::new(2).unwrap());
+        engine.execute(Command::ExecutorPageSelect { object_id: page }).unwrap();
+
+        let executor_id = ExecutorId::new(ObjectId::random(), slot);
+        engine.handle_trigger(Trigger::ExecutorMaster { executor_id, value: 0.5, relative: false });
+        assert_eq!(engine.objects().executor(&ExecutorId::new(page, slot)).unwrap().master(), 0.5);
+    }
+
+    #[test]
+    fn midi_feedback_on_page_select_and_cue_fired() {
+        let mut engine = example_engine();
+        let page = engine.objects().executor_pages().all()[0].id();
+        let colors = colors_executor(&engine);
+        let definition = serde_json::from_value(serde_json::json!({
+            "midi": [
+                {
+                    "device_name": "Faders",
+                    "channel": 0,
+                    "message": {
+                        "type": "ControlChange",
+                        "controller": 7,
+                        "value": { "from": 0, "to": 127 },
+                    },
+                    "target": {
+                        "type": "ExecutorMaster",
+                        "executor_id": { "page": page, "slot": 2 },
+                    },
+                },
+                {
+                    "device_name": "Pads",
+                    "channel": 0,
+                    "message": {
+                        "type": "NoteOn",
+                        "note": 36,
+                        "velocity": { "from": 0, "to": 127 },
+                    },
+                    "target": {
+                        "type": "ExecutorButton",
+                        "executor_id": colors,
+                        "button": "Button1",
+                    },
+                },
+            ],
+        }))
+        .unwrap();
+        engine.triggers_agent.set_definition(definition).unwrap();
+        let executor_id = ExecutorId::new(page, Slot::new(NonZeroU32::new(2).unwrap()));
+        engine.execute(Command::ExecutorSetMaster { executor_id, value: 1.0 }).unwrap();
+        engine.execute(Command::ExecutorSetEnabled { executor_id: colors, value: true }).unwrap();
+
+        let faders = ("Faders", vec![0xB0, 7, 127]);
+        let pads = ("Pads", vec![0x90, 36, 127]);
+
+        let agent = &engine.triggers_agent;
+
+        let selected = Event::ExecutorPageSelected { object_id: page };
+        let messages = agent.midi_feedback_messages(&selected, &engine.objects, Some(page));
+        assert_eq!(messages, [faders, pads.clone()]);
+
+        let fired = Event::CueFired { executor_id: colors, cue_index: 0, from_msc: false };
+        let messages = agent.midi_feedback_messages(&fired, &engine.objects, Some(page));
+        assert_eq!(messages, [pads]);
+    }
+
+    #[test]
+    fn load_captured_universe_into_programmer() {
+        let mut engine = example_engine();
+        let wash_1 = FixtureId::from(FixtureIdPart::new(201).unwrap());
+        let dimmer = Channel::new(153).unwrap();
+
+        let mut universe = dmx::Universe::new();
+        universe.set_value(&dimmer, Value(255));
+        engine.load_captured_universe(UniverseId::MIN, universe);
+
+        let programmer = engine.programmer().values();
+        assert!(programmer.get(&wash_1, &AttributeName::Dimmer).is_some());
+        assert!(engine.event_buffer.contains(&Event::ProgrammerChanged));
+
+        engine.resolve_pipeline();
+        let address = Address::new(UniverseId::MIN, dimmer);
+        assert_eq!(engine.pipeline().multiverse().value(&address), Some(Value(255)));
+    }
 }
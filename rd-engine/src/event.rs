@@ -1,8 +1,12 @@
 use std::path::PathBuf;
 
-use crate::object::{ObjectId, ObjectKind};
+use crate::object::{ExecutorId, ObjectId, ObjectKind};
+use crate::output::{OutputInstanceKind, OutputInstanceState};
+use crate::trigger::MidiTriggerDefinition;
 
 #[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
 pub enum Event {
     PipelineResolved,
     SelectionChanged,
@@ -10,6 +14,13 @@ pub enum Event {
     HighlightChanged { enabled: bool },
     ObjectChanged { object_kind: ObjectKind, object_id: ObjectId },
     EncoderChanged { encoder_ix: usize, value: f32 },
+    ExecutorPageSelected { object_id: ObjectId },
+    CueFired { executor_id: ExecutorId, cue_index: usize, from_msc: bool },
+    MidiTriggerLearned { definition: MidiTriggerDefinition },
+    MidiDevicesChanged,
+    SacnNetworkSourcesChanged,
+    OutputChanged,
+    OutputStateChanged { kind: OutputInstanceKind, index: usize, state: OutputInstanceState },
     Saved { path: PathBuf },
 }
 
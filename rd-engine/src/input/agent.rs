@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::dmx::{self, Multiverse, UniverseId};
+use crate::input::InputDefinition;
+use crate::input::capture::SacnCapture;
+
+pub struct InputAgent {
+    definition: InputDefinition,
+
+    multiverse: Arc<RwLock<Multiverse>>,
+
+    sacn: super::instance::sacn::SacnInput,
+    sacn_capture: Option<SacnCapture>,
+}
+
+impl Default for InputAgent {
+    fn default() -> Self {
+        Self::new(InputDefinition::default())
+    }
+}
+
+impl InputAgent {
+    pub fn new(definition: InputDefinition) -> Self {
+        let sacn = super::instance::sacn::SacnInput::new(definition.sacn.clone());
+
+        Self {
+            definition,
+            multiverse: Arc::new(RwLock::new(Multiverse::new())),
+            sacn,
+            sacn_capture: None,
+        }
+    }
+
+    pub fn definition(&self) -> &InputDefinition {
+        &self.definition
+    }
+
+    pub(crate) fn start(&mut self) {
+        log::debug!("Starting Input Agent...");
+
+        if let Err(err) = self.sacn.start(self.multiverse.clone()) {
+            log::error!("Failed to start sACN input: {err}");
+        }
+
+        log::info!("Started Input Agent");
+    }
+
+    pub(crate) fn stop(&mut self) {
+        log::debug!("Stopping Input Agent..");
+
+        self.sacn.stop();
+
+        log::info!("Stopped Input Agent");
+    }
+
+    /// The merged data of all received universes.
+    pub fn multiverse(&self) -> Multiverse {
+        self.multiverse.read().unwrap().clone()
+    }
+
+    /// The multiverse received universes are written into, shared with output instances
+    /// that also receive DMX.
+    pub(crate) fn shared_multiverse(&self) -> Arc<RwLock<Multiverse>> {
+        Arc::clone(&self.multiverse)
+    }
+
+    /// Merges (HTP) the received universes that are configured to do so into `output`.
+    pub(crate) fn merge_into_output(&self, output: &mut Multiverse) {
+        let multiverse = self.multiverse.read().unwrap();
+        for universe in self.definition.sacn.universes.iter().filter(|u| u.merge_into_output) {
+            let Some(input) = multiverse.universe(&universe.universe_id) else {
+                continue;
+            };
+
+            match output.universe_mut(&universe.universe_id) {
+                Some(output) => {
+                    for (output, input) in output.values_mut().iter_mut().zip(input.values()) {
+                        *output = (*output).max(*input);
+                    }
+                }
+                None => output.create_universe(universe.universe_id, input.clone()),
+            }
+        }
+    }
+
+    pub fn sacn(&self) -> &super::instance::sacn::SacnInput {
+        &self.sacn
+    }
+
+    pub fn sacn_network_sources(&self) -> Arc<Vec<super::instance::sacn::SacnNetworkSource>> {
+        self.sacn.network_sources()
+    }
+
+    /// Ignores the sACN data of the console's own output instances, so output that is
+    /// looped back over multicast isn't merged into itself.
+    pub(crate) fn set_own_sacn_cids(&mut self, cids: HashSet<Uuid>) {
+        self.sacn.set_ignored_sources(cids);
+    }
+
+    pub(crate) fn poll_sacn_network_sources(&mut self) -> bool {
+        self.sacn.poll_network_sources()
+    }
+
+    /// Starts listening to `universe_id` for `duration`, replacing a capture that is
+    /// still running. See [InputAgent::poll_sacn_capture].
+    pub(crate) fn start_sacn_capture(
+        &mut self,
+        universe_id: UniverseId,
+        duration: Duration,
+    ) -> anyhow::Result<()> {
+        self.sacn_capture = Some(self.sacn.capture(universe_id, duration)?);
+        log::info!("Started capturing sACN universe {universe_id}");
+        Ok(())
+    }
+
+    pub(crate) fn cancel_sacn_capture(&mut self) {
+        if self.sacn_capture.take().is_some() {
+            log::info!("Cancelled sACN capture");
+        }
+    }
+
+    pub fn is_capturing_sacn(&self) -> bool {
+        self.sacn_capture.is_some()
+    }
+
+    /// Finishes the sACN capture once it is done, returning the captured universe.
+    pub(crate) fn poll_sacn_capture(
+        &mut self,
+    ) -> Option<anyhow::Result<(UniverseId, dmx::Universe)>> {
+        let result = self.sacn_capture.as_ref()?.poll()?;
+        let capture = self.sacn_capture.take()?;
+        Some(result.map(|universe| (capture.universe_id(), universe)))
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    dmx::{self, UniverseId},
+    output::protocol::sacn::receiver::{MergeMode, Receiver},
+};
+
+/// Listens to a single sACN universe for a short time, to bring in a look from another
+/// console or a visualizer.
+///
+/// The universe is received by the sACN input, which runs the [CaptureRequest].
+pub(crate) struct SacnCapture {
+    universe_id: UniverseId,
+    result_rx: flume::Receiver<anyhow::Result<dmx::Universe>>,
+}
+
+impl SacnCapture {
+    pub fn new(universe_id: UniverseId, duration: Duration) -> (Self, CaptureRequest) {
+        let (result_tx, result_rx) = flume::bounded(1);
+        let request = CaptureRequest {
+            universe_id,
+            deadline: Instant::now() + duration,
+            result_tx,
+            joined: false,
+        };
+        (Self { universe_id, result_rx }, request)
+    }
+
+    pub fn universe_id(&self) -> UniverseId {
+        self.universe_id
+    }
+
+    /// The captured universe, once the capture has finished.
+    pub fn poll(&self) -> Option<anyhow::Result<dmx::Universe>> {
+        self.result_rx.try_recv().ok()
+    }
+}
+
+/// The receiving side of a [SacnCapture]. Dropping the capture cancels it.
+pub(crate) struct CaptureRequest {
+    universe_id: UniverseId,
+    deadline: Instant,
+    result_tx: flume::Sender<anyhow::Result<dmx::Universe>>,
+    /// Whether the universe was joined for this capture, and should be left afterwards.
+    joined: bool,
+}
+
+impl CaptureRequest {
+    /// Starts receiving the universe, unless the receiver already does.
+    pub fn start(&mut self, receiver: &mut Receiver) {
+        let universe = self.universe_id.as_u16();
+        if receiver.universes().any(|u| *u == universe) {
+            return;
+        }
+
+        self.joined = true;
+        // Unicast data is still received if joining the multicast group fails.
+        if let Err(err) = receiver.join_universe(universe) {
+            log::warn!("Failed to join sACN universe {}: {err}", self.universe_id);
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        Instant::now() >= self.deadline || self.result_tx.is_disconnected()
+    }
+
+    /// Sends the merged data of the universe to the [SacnCapture].
+    pub fn finish(self, receiver: &mut Receiver) {
+        let universe = self.universe_id.as_u16();
+        let result = match receiver.merge(universe, MergeMode::Priority) {
+            Some(data) => {
+                let mut captured = dmx::Universe::new();
+                for (value, slot) in captured.values_mut().iter_mut().zip(data) {
+                    *value = dmx::Value(slot);
+                }
+                Ok(captured)
+            }
+            None => Err(anyhow::anyhow!("No sACN data received on universe {}", self.universe_id)),
+        };
+        let _ = self.result_tx.send(result);
+
+        if self.joined
+            && let Err(err) = receiver.leave_universe(universe)
+        {
+            log::warn!("Failed to leave sACN universe {}: {err}", self.universe_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use super::*;
+    use crate::output::protocol::sacn::{
+        Universe,
+        receiver::ReceiverConfig,
+        source::{Source, SourceConfig},
+    };
+
+    fn run(receiver: &mut Receiver, mut request: CaptureRequest) {
+        request.start(receiver);
+        while !request.is_done() {
+            receiver.recv(Duration::from_millis(10)).unwrap();
+        }
+        request.finish(receiver);
+    }
+
+    #[test]
+    fn capture_over_loopback() {
+        let mut receiver = Receiver::new(ReceiverConfig { port: 0, interface: None }).unwrap();
+        let port = receiver.socket_port().unwrap();
+        let (capture, request) = SacnCapture::new(UniverseId::MIN, Duration::from_millis(300));
+
+        let source = Source::new(SourceConfig {
+            destinations: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, port))],
+            ..Default::default()
+        })
+        .unwrap();
+        let mut universe = Universe::new(1);
+        universe.data_slots.extend([255, 0, 127]);
+        source.send_universe_data_packet(universe).unwrap();
+
+        run(&mut receiver, request);
+        let captured = capture.poll().unwrap().unwrap();
+        assert_eq!(
+            captured.values()[..4].iter().map(|v| v.as_u8()).collect::<Vec<_>>(),
+            [255, 0, 127, 0]
+        );
+        assert_eq!(receiver.universes().count(), 0);
+
+        let (empty, request) = SacnCapture::new(UniverseId::MIN, Duration::ZERO);
+        run(&mut receiver, request);
+        assert!(empty.poll().unwrap().is_err());
+    }
+}
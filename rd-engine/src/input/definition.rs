@@ -0,0 +1,93 @@
+use std::net::Ipv4Addr;
+
+use crate::dmx::UniverseId;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct InputDefinition {
+    #[serde(default)]
+    pub(crate) sacn: SacnDmxInputDefinition,
+}
+
+impl InputDefinition {
+    pub fn sacn(&self) -> &SacnDmxInputDefinition {
+        &self.sacn
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SacnDmxInputDefinition {
+    /// Local address of the network interface to receive multicast on.
+    #[serde(default)]
+    pub(crate) interface: Option<Ipv4Addr>,
+    #[serde(default)]
+    pub(crate) universes: Vec<SacnDmxInputUniverseDefinition>,
+    /// Whether to listen for other sources announcing their universes. Off by default,
+    /// as the receiver binds the sACN port, which can take unicast data away from other
+    /// receivers on the same machine.
+    #[serde(default)]
+    pub(crate) discovery: bool,
+}
+
+impl SacnDmxInputDefinition {
+    pub fn interface(&self) -> Option<Ipv4Addr> {
+        self.interface
+    }
+
+    pub fn universes(&self) -> &[SacnDmxInputUniverseDefinition] {
+        &self.universes
+    }
+
+    pub fn discovery(&self) -> bool {
+        self.discovery
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SacnDmxInputUniverseDefinition {
+    pub(crate) universe_id: UniverseId,
+    #[serde(default)]
+    pub(crate) merge_mode: MergeMode,
+    /// Whether the received data is merged (HTP) into the engine output of the same universe.
+    #[serde(default)]
+    pub(crate) merge_into_output: bool,
+}
+
+impl SacnDmxInputUniverseDefinition {
+    pub fn universe_id(&self) -> UniverseId {
+        self.universe_id
+    }
+
+    pub fn merge_mode(&self) -> MergeMode {
+        self.merge_mode
+    }
+
+    pub fn merge_into_output(&self) -> bool {
+        self.merge_into_output
+    }
+}
+
+/// How the data of multiple sources for the same universe is combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub enum MergeMode {
+    /// Highest takes precedence across all sources, regardless of priority.
+    Htp,
+    /// Only the sources with the highest priority are used, merged HTP among themselves.
+    #[default]
+    Priority,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sacn_discovery_is_opt_in() {
+        let definition: InputDefinition = serde_json::from_str(r#"{ "sacn": {} }"#).unwrap();
+        assert!(!definition.sacn().discovery());
+        assert!(!InputDefinition::default().sacn().discovery());
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context as _;
+use uuid::Uuid;
+
+use crate::{
+    dmx::{self, Multiverse, UniverseId},
+    input::capture::{CaptureRequest, SacnCapture},
+    input::{MergeMode, SacnDmxInputDefinition, SacnDmxInputUniverseDefinition},
+    output::protocol::sacn::{
+        self, ComponentIdentifier,
+        receiver::{DiscoveredSource, Receiver, ReceiverConfig},
+    },
+};
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A source currently sending data to one of the received universes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SacnInputSource {
+    pub universe_id: UniverseId,
+    pub cid: Uuid,
+    pub name: String,
+    pub priority: u8,
+}
+
+/// A source on the network announcing the universes it sends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SacnNetworkSource {
+    pub cid: Uuid,
+    pub name: String,
+    pub universes: Vec<u16>,
+    pub last_seen: SystemTime,
+}
+
+pub struct SacnInput {
+    definition: SacnDmxInputDefinition,
+    sources: Arc<RwLock<Vec<SacnInputSource>>>,
+    network_sources: Arc<Vec<SacnNetworkSource>>,
+    network_sources_tx: flume::Sender<Vec<SacnNetworkSource>>,
+    network_sources_rx: flume::Receiver<Vec<SacnNetworkSource>>,
+    ignored_sources: HashSet<Uuid>,
+    ignored_sources_tx: flume::Sender<HashSet<Uuid>>,
+    ignored_sources_rx: flume::Receiver<HashSet<Uuid>>,
+    capture_tx: flume::Sender<CaptureRequest>,
+    capture_rx: flume::Receiver<CaptureRequest>,
+
+    multiverse: Option<Arc<RwLock<Multiverse>>>,
+    thread_handle: Option<JoinHandle<()>>,
+    thread_running: Arc<AtomicBool>,
+}
+
+impl SacnInput {
+    pub fn new(definition: SacnDmxInputDefinition) -> Self {
+        let (network_sources_tx, network_sources_rx) = flume::unbounded();
+        let (ignored_sources_tx, ignored_sources_rx) = flume::unbounded();
+        let (capture_tx, capture_rx) = flume::unbounded();
+
+        Self {
+            definition,
+            sources: Arc::new(RwLock::new(Vec::new())),
+            network_sources: Arc::new(Vec::new()),
+            network_sources_tx,
+            network_sources_rx,
+            ignored_sources: HashSet::new(),
+            ignored_sources_tx,
+            ignored_sources_rx,
+            capture_tx,
+            capture_rx,
+
+            multiverse: None,
+            thread_handle: None,
+            thread_running: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn start(&mut self, multiverse: Arc<RwLock<Multiverse>>) -> anyhow::Result<()> {
+        if self.thread_handle.is_some() {
+            log::warn!("sACN input thread already running");
+            return Ok(());
+        }
+
+        self.multiverse = Some(Arc::clone(&multiverse));
+        // The receiver is started on demand for captures.
+        if self.definition.universes.is_empty() && !self.definition.discovery {
+            return Ok(());
+        }
+
+        self.spawn_receiver(multiverse)
+    }
+
+    fn spawn_receiver(&mut self, multiverse: Arc<RwLock<Multiverse>>) -> anyhow::Result<()> {
+        let mut receiver = Receiver::new(ReceiverConfig {
+            interface: self.definition.interface,
+            ..Default::default()
+        })
+        .context("Failed to create sACN receiver")?;
+        receiver.set_ignored_sources(self.ignored_sources.clone());
+        // Changes made while stopped were already applied above.
+        self.ignored_sources_rx.drain();
+
+        for universe in &self.definition.universes {
+            if let Err(err) = receiver.join_universe(universe.universe_id.as_u16()) {
+                log::warn!("Failed to join sACN universe {}: {err}", universe.universe_id);
+            }
+        }
+
+        if self.definition.discovery
+            && let Err(err) = receiver.join_discovery()
+        {
+            log::warn!("Failed to join sACN universe discovery: {err}");
+        }
+
+        let universes = self.definition.universes.clone();
+        let sources = self.sources.clone();
+        let network_sources_tx = self.network_sources_tx.clone();
+        let ignored_sources_rx = self.ignored_sources_rx.clone();
+        let capture_rx = self.capture_rx.clone();
+        let running = self.thread_running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let handle = thread::Builder::new()
+            .name("rd_sacn_input".to_string())
+            .spawn(move || {
+                let mut discovered = Vec::new();
+                let mut captures = Vec::new();
+                while running.load(Ordering::SeqCst) {
+                    if let Some(ignored_sources) = ignored_sources_rx.try_iter().last() {
+                        receiver.set_ignored_sources(ignored_sources);
+                    }
+
+                    for mut capture in capture_rx.try_iter() {
+                        capture.start(&mut receiver);
+                        captures.push(capture);
+                    }
+
+                    match receiver.recv(RECEIVE_TIMEOUT) {
+                        Ok(Some(_)) => {
+                            // Lost sources can affect several universes at once.
+                            for universe in &universes {
+                                update_universe(&receiver, universe, &multiverse);
+                            }
+                            update_sources(&receiver, &universes, &sources);
+
+                            let current = discovered_sources(&receiver);
+                            if current != discovered {
+                                let _ = network_sources_tx.send(network_sources(&current));
+                                discovered = current;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => log::warn!("sACN input failed to receive: {err}"),
+                    }
+
+                    let (done, pending) = std::mem::take(&mut captures)
+                        .into_iter()
+                        .partition::<Vec<_>, _>(CaptureRequest::is_done);
+                    for capture in done {
+                        capture.finish(&mut receiver);
+                    }
+                    captures = pending;
+                }
+
+                let mut multiverse = multiverse.write().unwrap();
+                for universe in &universes {
+                    multiverse.remove_universe(&universe.universe_id);
+                }
+                sources.write().unwrap().clear();
+                let _ = network_sources_tx.send(Vec::new());
+            })
+            .context("Failed to spawn sACN input thread")?;
+
+        self.thread_handle = Some(handle);
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.multiverse = None;
+        if self.thread_handle.is_some() {
+            self.thread_running.store(false, Ordering::SeqCst);
+            if let Some(handle) = self.thread_handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    pub fn definition(&self) -> &SacnDmxInputDefinition {
+        &self.definition
+    }
+
+    /// The sources currently sending data to the received universes.
+    pub fn sources(&self) -> Vec<SacnInputSource> {
+        self.sources.read().unwrap().clone()
+    }
+
+    /// The sources on the network that announced their universes, as of the last
+    /// [SacnInput::poll_network_sources].
+    pub fn network_sources(&self) -> Arc<Vec<SacnNetworkSource>> {
+        Arc::clone(&self.network_sources)
+    }
+
+    /// Listens to `universe_id` for `duration` on this input's receiver, starting the
+    /// receiver if no universes are received otherwise.
+    pub(crate) fn capture(
+        &mut self,
+        universe_id: UniverseId,
+        duration: Duration,
+    ) -> anyhow::Result<SacnCapture> {
+        if self.thread_handle.is_none() {
+            let multiverse = self.multiverse.clone().context("sACN input is not running")?;
+            self.spawn_receiver(multiverse)?;
+        }
+
+        let (capture, request) = SacnCapture::new(universe_id, duration);
+        self.capture_tx.send(request).context("sACN input thread stopped")?;
+        Ok(capture)
+    }
+
+    /// Drops data sent by these sources, e.g. the sACN output instances of this console.
+    pub(crate) fn set_ignored_sources(&mut self, cids: HashSet<Uuid>) {
+        if cids == self.ignored_sources {
+            return;
+        }
+        self.ignored_sources = cids.clone();
+        let _ = self.ignored_sources_tx.send(cids);
+    }
+
+    /// Picks up changes to the network sources. Returns `true` if they changed.
+    pub(crate) fn poll_network_sources(&mut self) -> bool {
+        match self.network_sources_rx.try_iter().last() {
+            Some(network_sources) => {
+                self.network_sources = Arc::new(network_sources);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for SacnInput {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn update_universe(
+    receiver: &Receiver,
+    universe: &SacnDmxInputUniverseDefinition,
+    multiverse: &RwLock<Multiverse>,
+) {
+    let mode = match universe.merge_mode {
+        MergeMode::Htp => sacn::receiver::MergeMode::Htp,
+        MergeMode::Priority => sacn::receiver::MergeMode::Priority,
+    };
+
+    let mut multiverse = multiverse.write().unwrap();
+    match receiver.merge(universe.universe_id.as_u16(), mode) {
+        Some(data) => {
+            let mut merged = dmx::Universe::new();
+            for (value, slot) in merged.values_mut().iter_mut().zip(data) {
+                *value = dmx::Value(slot);
+            }
+            multiverse.create_universe(universe.universe_id, merged);
+        }
+        None => {
+            multiverse.remove_universe(&universe.universe_id);
+        }
+    }
+}
+
+fn update_sources(
+    receiver: &Receiver,
+    universes: &[SacnDmxInputUniverseDefinition],
+    sources: &RwLock<Vec<SacnInputSource>>,
+) {
+    let current = universes
+        .iter()
+        .flat_map(|universe| {
+            receiver.sources(universe.universe_id.as_u16()).map(|(cid, source)| SacnInputSource {
+                universe_id: universe.universe_id,
+                cid: *cid,
+                name: source.name.clone(),
+                priority: source.priority,
+            })
+        })
+        .collect();
+
+    *sources.write().unwrap() = current;
+}
+
+fn discovered_sources(receiver: &Receiver) -> Vec<(ComponentIdentifier, DiscoveredSource)> {
+    let mut discovered = receiver
+        .discovered_sources()
+        .map(|(cid, source)| (*cid, source.clone()))
+        .collect::<Vec<_>>();
+    discovered.sort_by_key(|(cid, _)| *cid);
+    discovered
+}
+
+fn network_sources(
+    discovered: &[(ComponentIdentifier, DiscoveredSource)],
+) -> Vec<SacnNetworkSource> {
+    discovered
+        .iter()
+        .map(|(cid, source)| SacnNetworkSource {
+            cid: *cid,
+            name: source.name.clone(),
+            universes: source.universes().copied().collect(),
+            last_seen: SystemTime::now() - source.last_seen.elapsed(),
+        })
+        .collect()
+}
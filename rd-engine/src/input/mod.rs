@@ -0,0 +1,8 @@
+mod agent;
+mod capture;
+mod definition;
+mod instance;
+
+pub use agent::*;
+pub use definition::*;
+pub use instance::sacn::SacnNetworkSource;
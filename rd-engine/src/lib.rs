@@ -1,11 +1,13 @@
 pub mod cmd;
 pub mod dmx;
 pub mod event;
+pub mod input;
 pub mod object;
 pub mod output;
 pub mod patch;
 pub mod pipeline;
 pub mod programmer;
+pub mod remote;
 pub mod selection;
 pub mod trigger;
 pub mod value;
@@ -143,6 +143,9 @@ pub struct SequenceExecutorContent {
 
     #[serde(skip, default = "Instant::now")]
     pub(crate) last_activation_time: Instant,
+    /// The cue the next go jumps to, instead of the following one.
+    #[serde(skip)]
+    pub(crate) loaded_cue_index: Option<usize>,
 }
 
 impl SequenceExecutorContent {
@@ -185,6 +188,10 @@ impl SequenceExecutorContent {
     pub(crate) fn last_activation_time(&self) -> Instant {
         self.last_activation_time
     }
+
+    pub fn loaded_cue_index(&self) -> Option<usize> {
+        self.loaded_cue_index
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -207,6 +214,11 @@ impl ExecutorId {
     pub fn new(page: ObjectId, slot: Slot) -> Self {
         Self { page, slot }
     }
+
+    /// The executor in the same slot on `selected_page`, if a page is selected.
+    pub fn on_selected_page(self, selected_page: Option<ObjectId>) -> Self {
+        Self { page: selected_page.unwrap_or(self.page), slot: self.slot }
+    }
 }
 
 impl fmt::Display for ExecutorId {
@@ -205,6 +205,17 @@ impl Objects {
         })
     }
 
+    pub fn executor(&self, executor_id: &ExecutorId) -> anyhow::Result<&Executor> {
+        self.executor_pages.get_by_object_id(&executor_id.page)?.executor(executor_id.slot)
+    }
+
+    pub fn current_cue(&self, executor: &Executor) -> Option<&Cue> {
+        let Some(ExecutorContent::Sequence(sc)) = executor.content() else {
+            return None;
+        };
+        self.sequences.get_by_object_id(&sc.sequence()).ok()?.cue(sc.cue_index()).ok()
+    }
+
     pub fn groups(&self) -> &ObjectCollection<Group> {
         &self.groups
     }
@@ -1,30 +1,43 @@
-use std::sync::RwLock;
-use std::thread::{self, JoinHandle};
-use std::{
-    sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
-    },
-    time::{Duration, Instant},
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+
+use crate::dmx::{Address, Multiverse};
+use crate::output::instance::{
+    artnet::ArtNetInstance, enttec::EnttecInstance, loopback::LoopbackInstance, sacn::SacnInstance,
+    serial::SerialInstance,
 };
+use crate::output::recording::{Playback, Player, Recorder};
+use crate::output::routing::Router;
+use crate::output::status::{InstanceHealth, OutputInstanceStatus};
+use crate::output::{OutputDefinition, OutputInstanceDefinition, OutputInstanceKind};
 
-use thread_priority::{ThreadBuilderExt as _, ThreadPriority};
-
-use crate::dmx::Multiverse;
-use crate::output::OutputDefinition;
+/// How often the statistics in the statuses are refreshed.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct OutputAgent {
     definition: OutputDefinition,
+    running: bool,
 
-    scheduler_handle: Option<JoinHandle<()>>,
-    scheduler_running: Arc<AtomicBool>,
-
-    notify_tx: flume::Sender<()>,
-    notify_rx: flume::Receiver<()>,
+    router: Router,
     multiverse: Arc<RwLock<Multiverse>>,
-
-    sacn_instances: Vec<super::instance::sacn::SacnInstance>,
-    enttec_instances: Vec<super::instance::enttec::EnttecInstance>,
+    patched_addresses: HashSet<Address>,
+    statuses: Arc<Vec<OutputInstanceStatus>>,
+    last_status_time: Option<Instant>,
+    input_multiverse: Arc<RwLock<Multiverse>>,
+    recording: Option<(Recorder<BufWriter<File>>, Instant)>,
+    playback: Option<Playback<BufReader<File>>>,
+
+    sacn_instances: Vec<SacnInstance>,
+    enttec_instances: Vec<EnttecInstance>,
+    artnet_instances: Vec<ArtNetInstance>,
+    serial_instances: Vec<SerialInstance>,
+    loopback_instances: Vec<LoopbackInstance>,
 }
 
 impl Default for OutputAgent {
@@ -35,35 +48,62 @@ impl Default for OutputAgent {
 
 impl OutputAgent {
     pub fn new(definition: OutputDefinition) -> anyhow::Result<Self> {
-        let (notify_tx, notify_rx) = flume::bounded(1);
         let multiverse = Arc::new(RwLock::new(Multiverse::new()));
+        let router = Router::new(&definition.routing);
 
         let sacn_instances = definition
             .sacn
             .instances()
             .iter()
-            .map(|instance| super::instance::sacn::SacnInstance::new(instance.clone()))
+            .map(|instance| SacnInstance::new(instance.clone()))
             .collect::<anyhow::Result<Vec<_>>>()?;
 
         let enttec_instances = definition
             .enttec
             .instances()
             .iter()
-            .map(|instance| super::instance::enttec::EnttecInstance::new(instance.clone()))
+            .map(|instance| EnttecInstance::new(instance.clone()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let artnet_instances = definition
+            .artnet
+            .instances()
+            .iter()
+            .map(|instance| ArtNetInstance::new(instance.clone()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let serial_instances = definition
+            .serial
+            .instances()
+            .iter()
+            .map(|instance| SerialInstance::new(instance.clone()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let loopback_instances = definition
+            .loopback
+            .instances()
+            .iter()
+            .map(|instance| LoopbackInstance::new(instance.clone()))
             .collect::<anyhow::Result<Vec<_>>>()?;
 
         Ok(Self {
             definition,
+            running: false,
 
-            scheduler_handle: None,
-            scheduler_running: Arc::new(AtomicBool::new(false)),
-
-            notify_tx,
-            notify_rx,
+            router,
             multiverse,
+            patched_addresses: HashSet::new(),
+            statuses: Arc::new(Vec::new()),
+            last_status_time: None,
+            input_multiverse: Arc::new(RwLock::new(Multiverse::new())),
+            recording: None,
+            playback: None,
 
             sacn_instances,
             enttec_instances,
+            artnet_instances,
+            serial_instances,
+            loopback_instances,
         })
     }
 
@@ -74,19 +114,15 @@ impl OutputAgent {
     pub(crate) fn start(&mut self) {
         log::debug!("Starting Output Agent...");
 
-        for instance in &mut self.sacn_instances {
-            if let Err(err) = instance.start(self.notify_rx.clone(), self.multiverse.clone()) {
-                log::error!("Failed to start sACN output instance: {err}");
-            }
-        }
-
-        for instance in &mut self.enttec_instances {
-            if let Err(err) = instance.start(self.notify_rx.clone(), self.multiverse.clone()) {
-                log::error!("Failed to start Enttec output instance: {err}");
+        for kind in OutputInstanceKind::ALL {
+            for index in 0..self.instance_count(kind) {
+                if let Err(err) = self.start_instance(kind, index) {
+                    log::error!("Failed to start {kind} output instance: {err}");
+                }
             }
         }
 
-        self.start_scheduler();
+        self.running = true;
 
         log::info!("Started Output Agent");
     }
@@ -94,88 +130,515 @@ impl OutputAgent {
     pub(crate) fn stop(&mut self) {
         log::debug!("Stopping Output Agent..");
 
-        self.stop_scheduler();
+        self.running = false;
 
-        for instance in &mut self.sacn_instances {
-            instance.stop();
+        for kind in OutputInstanceKind::ALL {
+            for index in 0..self.instance_count(kind) {
+                self.stop_instance(kind, index);
+            }
+        }
+
+        log::info!("Stopped Output Agent");
+    }
+
+    /// Adds an instance to the definition, and starts it if the agent is running.
+    pub(crate) fn add_instance(
+        &mut self,
+        definition: OutputInstanceDefinition,
+    ) -> anyhow::Result<()> {
+        let kind = definition.kind();
+        match definition {
+            OutputInstanceDefinition::Sacn(definition) => {
+                self.sacn_instances.push(SacnInstance::new(definition.clone())?);
+                self.definition.sacn.instances.push(definition);
+            }
+            OutputInstanceDefinition::Enttec(definition) => {
+                self.enttec_instances.push(EnttecInstance::new(definition.clone())?);
+                self.definition.enttec.instances.push(definition);
+            }
+            OutputInstanceDefinition::ArtNet(definition) => {
+                self.artnet_instances.push(ArtNetInstance::new(definition.clone())?);
+                self.definition.artnet.instances.push(definition);
+            }
+            OutputInstanceDefinition::Serial(definition) => {
+                self.serial_instances.push(SerialInstance::new(definition.clone())?);
+                self.definition.serial.instances.push(definition);
+            }
+            OutputInstanceDefinition::Loopback(definition) => {
+                self.loopback_instances.push(LoopbackInstance::new(definition.clone())?);
+                self.definition.loopback.instances.push(definition);
+            }
         }
 
-        for instance in &mut self.enttec_instances {
-            instance.stop();
+        if self.is_running() {
+            self.start_instance(kind, self.instance_count(kind) - 1)?;
         }
 
-        log::info!("Stopped Output Agent");
+        Ok(())
     }
 
-    fn start_scheduler(&mut self) {
-        const INTERVAL: Duration = Duration::new(0, ((1_000_000_000_f64 / 44.0).round()) as u32);
-
-        if self.scheduler_handle.is_some() {
-            log::warn!("Scheduler already running");
-            return;
-        }
-
-        let running = self.scheduler_running.clone();
-        running.store(true, Ordering::SeqCst);
-
-        let notify_tx = self.notify_tx.clone();
-        let handle = thread::Builder::new()
-            .name("rd_output_scheduler".to_string())
-            .spawn_with_priority(ThreadPriority::Max, move |tp_res| {
-                if let Err(err) = tp_res {
-                    log::warn!("Failed to set thread priority: {err}");
-                };
-
-                let sleeper = spin_sleep::SpinSleeper::default();
-                let mut next_tick = Instant::now() + INTERVAL;
-                while running.load(Ordering::SeqCst) {
-                    let now = Instant::now();
-                    if now < next_tick {
-                        sleeper.sleep_until(next_tick);
-                    } else {
-                        let deviation = (now - next_tick).as_secs_f64();
-                        if now > next_tick + INTERVAL {
-                            // If we are more than one tick late, skip ahead to catch up.
-                            let ticks_missed =
-                                (deviation / INTERVAL.as_secs_f64()).floor() as u32 + 1;
-                            next_tick += INTERVAL * ticks_missed;
-                        }
-                    }
-
-                    let _ = notify_tx.try_send(());
-
-                    next_tick += INTERVAL;
-                }
-            })
-            .expect("Failed to spawn scheduler thread");
+    /// Replaces the definition of the instance of the same kind at `index`, restarting
+    /// only that instance. The current instance is kept if the new one fails to start.
+    pub(crate) fn edit_instance(
+        &mut self,
+        index: usize,
+        definition: OutputInstanceDefinition,
+    ) -> anyhow::Result<()> {
+        let kind = definition.kind();
+        self.ensure_instance_exists(kind, index)?;
+
+        // The new instance is created before the current one is stopped, so an invalid
+        // definition leaves the current one running.
+        match &definition {
+            OutputInstanceDefinition::Sacn(definition) => {
+                let instance = SacnInstance::new(definition.clone())?;
+                self.stop_instance(kind, index);
+                self.sacn_instances[index] = instance;
+            }
+            OutputInstanceDefinition::Enttec(definition) => {
+                let instance = EnttecInstance::new(definition.clone())?;
+                self.stop_instance(kind, index);
+                self.enttec_instances[index] = instance;
+            }
+            OutputInstanceDefinition::ArtNet(definition) => {
+                let instance = ArtNetInstance::new(definition.clone())?;
+                self.stop_instance(kind, index);
+                self.artnet_instances[index] = instance;
+            }
+            OutputInstanceDefinition::Serial(definition) => {
+                let instance = SerialInstance::new(definition.clone())?;
+                self.stop_instance(kind, index);
+                self.serial_instances[index] = instance;
+            }
+            OutputInstanceDefinition::Loopback(definition) => {
+                let instance = LoopbackInstance::new(definition.clone())?;
+                self.stop_instance(kind, index);
+                self.loopback_instances[index] = instance;
+            }
+        }
+
+        if self.is_running()
+            && let Err(err) = self.start_instance(kind, index)
+        {
+            self.stop_instance(kind, index);
+            if let Err(restore_err) = self.restore_instance(kind, index) {
+                log::error!("Failed to restore {kind} output instance: {restore_err:#}");
+            }
+            return Err(err);
+        }
+
+        match definition {
+            OutputInstanceDefinition::Sacn(definition) => {
+                self.definition.sacn.instances[index] = definition;
+            }
+            OutputInstanceDefinition::Enttec(definition) => {
+                self.definition.enttec.instances[index] = definition;
+            }
+            OutputInstanceDefinition::ArtNet(definition) => {
+                self.definition.artnet.instances[index] = definition;
+            }
+            OutputInstanceDefinition::Serial(definition) => {
+                self.definition.serial.instances[index] = definition;
+            }
+            OutputInstanceDefinition::Loopback(definition) => {
+                self.definition.loopback.instances[index] = definition;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stops the instance of `kind` at `index` and removes it from the definition.
+    pub(crate) fn remove_instance(
+        &mut self,
+        kind: OutputInstanceKind,
+        index: usize,
+    ) -> anyhow::Result<()> {
+        self.ensure_instance_exists(kind, index)?;
+
+        self.stop_instance(kind, index);
+        match kind {
+            OutputInstanceKind::Sacn => {
+                self.sacn_instances.remove(index);
+                self.definition.sacn.instances.remove(index);
+            }
+            OutputInstanceKind::Enttec => {
+                self.enttec_instances.remove(index);
+                self.definition.enttec.instances.remove(index);
+            }
+            OutputInstanceKind::ArtNet => {
+                self.artnet_instances.remove(index);
+                self.definition.artnet.instances.remove(index);
+            }
+            OutputInstanceKind::Serial => {
+                self.serial_instances.remove(index);
+                self.definition.serial.instances.remove(index);
+            }
+            OutputInstanceKind::Loopback => {
+                self.loopback_instances.remove(index);
+                self.definition.loopback.instances.remove(index);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn instance_count(&self, kind: OutputInstanceKind) -> usize {
+        match kind {
+            OutputInstanceKind::Sacn => self.sacn_instances.len(),
+            OutputInstanceKind::Enttec => self.enttec_instances.len(),
+            OutputInstanceKind::ArtNet => self.artnet_instances.len(),
+            OutputInstanceKind::Serial => self.serial_instances.len(),
+            OutputInstanceKind::Loopback => self.loopback_instances.len(),
+        }
+    }
+
+    fn ensure_instance_exists(&self, kind: OutputInstanceKind, index: usize) -> anyhow::Result<()> {
+        if index >= self.instance_count(kind) {
+            anyhow::bail!("No {kind} output instance at index {index}");
+        }
+        Ok(())
+    }
+
+    fn start_instance(&mut self, kind: OutputInstanceKind, index: usize) -> anyhow::Result<()> {
+        let multiverse = self.multiverse.clone();
+        let result = match kind {
+            OutputInstanceKind::Sacn => {
+                self.sacn_instances[index].start(multiverse, &self.patched_addresses)
+            }
+            OutputInstanceKind::Enttec => {
+                self.enttec_instances[index].start(multiverse, self.input_multiverse.clone())
+            }
+            OutputInstanceKind::ArtNet => self.artnet_instances[index].start(multiverse),
+            OutputInstanceKind::Serial => self.serial_instances[index].start(multiverse),
+            OutputInstanceKind::Loopback => self.loopback_instances[index].start(multiverse),
+        };
+
+        if let Err(err) = &result {
+            self.instance_health(kind, index).error(format!("{err:#}"));
+        }
+        result
+    }
+
+    /// Recreates the instance of `kind` at `index` from the definition, and starts it if
+    /// the agent is running.
+    fn restore_instance(&mut self, kind: OutputInstanceKind, index: usize) -> anyhow::Result<()> {
+        match kind {
+            OutputInstanceKind::Sacn => {
+                let definition = self.definition.sacn.instances[index].clone();
+                self.sacn_instances[index] = SacnInstance::new(definition)?;
+            }
+            OutputInstanceKind::Enttec => {
+                let definition = self.definition.enttec.instances[index].clone();
+                self.enttec_instances[index] = EnttecInstance::new(definition)?;
+            }
+            OutputInstanceKind::ArtNet => {
+                let definition = self.definition.artnet.instances[index].clone();
+                self.artnet_instances[index] = ArtNetInstance::new(definition)?;
+            }
+            OutputInstanceKind::Serial => {
+                let definition = self.definition.serial.instances[index].clone();
+                self.serial_instances[index] = SerialInstance::new(definition)?;
+            }
+            OutputInstanceKind::Loopback => {
+                let definition = self.definition.loopback.instances[index].clone();
+                self.loopback_instances[index] = LoopbackInstance::new(definition)?;
+            }
+        }
 
-        self.scheduler_handle = Some(handle);
+        if self.is_running() {
+            self.start_instance(kind, index)?;
+        }
+        Ok(())
     }
 
-    fn stop_scheduler(&mut self) {
-        if self.scheduler_handle.is_some() {
-            self.scheduler_running.store(false, Ordering::SeqCst);
-            if let Some(handle) = self.scheduler_handle.take() {
-                let _ = handle.join();
+    fn stop_instance(&mut self, kind: OutputInstanceKind, index: usize) {
+        match kind {
+            OutputInstanceKind::Sacn => self.sacn_instances[index].stop(),
+            OutputInstanceKind::Enttec => self.enttec_instances[index].stop(),
+            OutputInstanceKind::ArtNet => self.artnet_instances[index].stop(),
+            OutputInstanceKind::Serial => self.serial_instances[index].stop(),
+            OutputInstanceKind::Loopback => self.loopback_instances[index].stop(),
+        }
+    }
+
+    fn instance_health(&self, kind: OutputInstanceKind, index: usize) -> &InstanceHealth {
+        match kind {
+            OutputInstanceKind::Sacn => self.sacn_instances[index].health(),
+            OutputInstanceKind::Enttec => self.enttec_instances[index].health(),
+            OutputInstanceKind::ArtNet => self.artnet_instances[index].health(),
+            OutputInstanceKind::Serial => self.serial_instances[index].health(),
+            OutputInstanceKind::Loopback => self.loopback_instances[index].health(),
+        }
+    }
+
+    fn instance_status(&self, kind: OutputInstanceKind, index: usize) -> OutputInstanceStatus {
+        let (name, cid) = match kind {
+            OutputInstanceKind::Sacn => {
+                let instance = &self.sacn_instances[index];
+                (instance.name().to_string(), Some(instance.cid()))
+            }
+            OutputInstanceKind::Enttec => (self.enttec_instances[index].name().to_string(), None),
+            OutputInstanceKind::ArtNet => (self.artnet_instances[index].name().to_string(), None),
+            OutputInstanceKind::Serial => (self.serial_instances[index].path().to_string(), None),
+            OutputInstanceKind::Loopback => {
+                (self.loopback_instances[index].name().to_string(), None)
             }
+        };
+        self.instance_health(kind, index).status(kind, index, name, cid)
+    }
+
+    /// The status of every instance, as of the last [OutputAgent::poll_statuses].
+    pub fn statuses(&self) -> Arc<Vec<OutputInstanceStatus>> {
+        Arc::clone(&self.statuses)
+    }
+
+    /// Refreshes the statuses when an instance changed state, or at least every
+    /// [STATUS_INTERVAL] to update the statistics.
+    ///
+    /// Returns the statuses of the instances whose state changed, or `None` if the
+    /// statuses weren't refreshed.
+    pub(crate) fn poll_statuses(&mut self) -> Option<Vec<OutputInstanceStatus>> {
+        let statuses = OutputInstanceKind::ALL
+            .into_iter()
+            .flat_map(|kind| (0..self.instance_count(kind)).map(move |index| (kind, index)))
+            .map(|(kind, index)| self.instance_status(kind, index))
+            .collect::<Vec<_>>();
+
+        let changed = statuses
+            .iter()
+            .filter(|status| {
+                !self.statuses.iter().any(|previous| {
+                    previous.kind == status.kind
+                        && previous.index == status.index
+                        && previous.state == status.state
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let due = self.last_status_time.is_none_or(|time| time.elapsed() >= STATUS_INTERVAL);
+        if changed.is_empty() && statuses.len() == self.statuses.len() && !due {
+            return None;
         }
+
+        self.statuses = Arc::new(statuses);
+        self.last_status_time = Some(Instant::now());
+        Some(changed)
+    }
+
+    /// Sets the addresses driven by patched fixtures, used for sACN per-address priority.
+    ///
+    /// Takes effect when the instances are (re)started.
+    pub(crate) fn set_patched_addresses(&mut self, patched_addresses: HashSet<Address>) {
+        self.patched_addresses = self.router.route_addresses(&patched_addresses);
+    }
+
+    /// Sets the multiverse that instances receiving DMX (like the Enttec DMX USB Pro) write into.
+    ///
+    /// Takes effect when the instances are (re)started.
+    pub(crate) fn set_input_multiverse(&mut self, input_multiverse: Arc<RwLock<Multiverse>>) {
+        self.input_multiverse = input_multiverse;
     }
 
-    pub(crate) fn update(&self, multiverse: Multiverse) {
+    /// Routes `multiverse` to the output universes and hands it to the instances.
+    ///
+    /// During playback the recorded frames are sent instead.
+    pub(crate) fn update(&mut self, multiverse: Multiverse) {
+        let multiverse = match self.playback_frame() {
+            Some(frame) => frame,
+            None => self.router.route(multiverse),
+        };
+
+        if let Some((recorder, start)) = &mut self.recording
+            && let Err(err) = recorder.record(start.elapsed(), &multiverse)
+        {
+            log::error!("Failed to record output, stopping the recording: {err}");
+            self.recording = None;
+        }
+
         *self.multiverse.write().unwrap() = multiverse;
     }
 
-    pub fn sacn_instances(&self) -> &[super::instance::sacn::SacnInstance] {
+    /// Starts recording the frames sent to the instances to the file at `path`.
+    pub(crate) fn start_recording(&mut self, path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording file: {}", path.display()))?;
+        let recorder = Recorder::new(BufWriter::new(file))?;
+        self.recording = Some((recorder, Instant::now()));
+        log::info!("Started recording output to {}", path.display());
+        Ok(())
+    }
+
+    pub(crate) fn stop_recording(&mut self) -> anyhow::Result<()> {
+        if let Some((recorder, _)) = self.recording.take() {
+            recorder.finish().context("Failed to finish recording")?;
+            log::info!("Stopped recording output");
+        }
+        Ok(())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Sends the frames of the recording at `path` instead of the pipeline output, until
+    /// it ends or [OutputAgent::stop_playback] is called.
+    pub(crate) fn start_playback(&mut self, path: &Path) -> anyhow::Result<()> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open recording file: {}", path.display()))?;
+        let player = Player::new(BufReader::new(file)).context("Failed to read recording")?;
+        self.playback = Some(Playback::new(player)?);
+        log::info!("Started playback of {}", path.display());
+        Ok(())
+    }
+
+    pub(crate) fn stop_playback(&mut self) {
+        self.playback = None;
+    }
+
+    pub fn is_playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    fn playback_frame(&mut self) -> Option<Multiverse> {
+        let playback = self.playback.as_mut()?;
+        match playback.current() {
+            Ok(Some(frame)) => Some(frame.clone()),
+            Ok(None) => {
+                log::info!("Playback ended");
+                self.playback = None;
+                None
+            }
+            Err(err) => {
+                log::error!("Failed to read recording, stopping playback: {err}");
+                self.playback = None;
+                None
+            }
+        }
+    }
+
+    pub fn sacn_instances(&self) -> &[SacnInstance] {
         &self.sacn_instances
     }
 
-    pub fn enttec_instances(&self) -> &[super::instance::enttec::EnttecInstance] {
+    pub fn enttec_instances(&self) -> &[EnttecInstance] {
         &self.enttec_instances
     }
+
+    pub fn artnet_instances(&self) -> &[ArtNetInstance] {
+        &self.artnet_instances
+    }
+
+    pub fn serial_instances(&self) -> &[SerialInstance] {
+        &self.serial_instances
+    }
+
+    pub fn loopback_instances(&self) -> &[LoopbackInstance] {
+        &self.loopback_instances
+    }
 }
 
 impl Drop for OutputAgent {
     fn drop(&mut self) {
-        self.stop_scheduler();
+        if self.running {
+            self.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+    use crate::dmx::UniverseId;
+    use crate::output::{
+        ArtNetDmxOutputInstanceDefinition, LoopbackOutputInstanceDefinition, OutputInstanceState,
+    };
+
+    fn artnet(name: &str) -> OutputInstanceDefinition {
+        OutputInstanceDefinition::ArtNet(ArtNetDmxOutputInstanceDefinition {
+            name: name.to_string(),
+            universe_ids: vec![UniverseId::MIN],
+            target_addresses: Vec::new(),
+            broadcast_address: Ipv4Addr::BROADCAST,
+            sync: false,
+            refresh_rate: 44.0,
+            send_on_change: false,
+            keepalive_interval_ms: 1000,
+        })
+    }
+
+    #[test]
+    fn add_edit_and_remove_instances() {
+        let mut agent = OutputAgent::default();
+
+        agent.add_instance(artnet("a")).unwrap();
+        agent.add_instance(artnet("b")).unwrap();
+        agent.edit_instance(1, artnet("c")).unwrap();
+        assert!(agent.edit_instance(2, artnet("d")).is_err());
+
+        let names = |agent: &OutputAgent| {
+            agent
+                .definition()
+                .artnet()
+                .instances()
+                .iter()
+                .map(|i| i.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&agent), ["a", "c"]);
+        assert_eq!(agent.artnet_instances()[1].name(), "c");
+
+        agent.remove_instance(OutputInstanceKind::ArtNet, 0).unwrap();
+        assert_eq!(names(&agent), ["c"]);
+        assert_eq!(agent.artnet_instances().len(), 1);
+    }
+
+    #[test]
+    fn failed_edit_keeps_the_running_instance() {
+        let loopback = |name: &str, refresh_rate| {
+            OutputInstanceDefinition::Loopback(LoopbackOutputInstanceDefinition {
+                name: name.to_string(),
+                universe_ids: vec![UniverseId::MIN],
+                refresh_rate,
+            })
+        };
+
+        let mut agent = OutputAgent::default();
+        agent.add_instance(loopback("a", 44.0)).unwrap();
+        agent.start();
+
+        assert!(agent.edit_instance(0, loopback("b", 0.0)).is_err());
+        assert_eq!(agent.definition().loopback().instances()[0].name, "a");
+        assert_eq!(agent.loopback_instances()[0].name(), "a");
+        assert!(agent.loopback_instances()[0].subscribe().recv().is_ok());
+
+        agent.stop();
+    }
+
+    #[test]
+    fn poll_statuses() {
+        let mut agent = OutputAgent::default();
+        agent.add_instance(artnet("a")).unwrap();
+
+        let changed = agent.poll_statuses().unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].state, OutputInstanceState::Stopped);
+        assert!(agent.poll_statuses().is_none());
+
+        agent.artnet_instances[0].health().error("unreachable");
+        let changed = agent.poll_statuses().unwrap();
+        assert_eq!(changed[0].state, OutputInstanceState::Error);
+        assert_eq!(agent.statuses()[0].last_error.as_deref(), Some("unreachable"));
+
+        agent.artnet_instances[0].health().frame_sent();
+        assert_eq!(agent.poll_statuses().unwrap()[0].state, OutputInstanceState::Running);
+        assert_eq!(agent.statuses()[0].frames_sent, 1);
     }
 }
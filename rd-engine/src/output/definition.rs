@@ -1,12 +1,23 @@
-use std::net::SocketAddr;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
-use crate::dmx::UniverseId;
+use uuid::Uuid;
+
+use crate::dmx::{Channel, UniverseId};
 
 #[derive(Debug, Clone, PartialEq, Default)]
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct OutputDefinition {
     pub(crate) sacn: SacnDmxOutputDefinition,
     pub(crate) enttec: EnttecDmxOutputDefinition,
+    #[serde(default)]
+    pub(crate) artnet: ArtNetDmxOutputDefinition,
+    #[serde(default)]
+    pub(crate) serial: SerialDmxOutputDefinition,
+    #[serde(default)]
+    pub(crate) loopback: LoopbackOutputDefinition,
+    #[serde(default)]
+    pub(crate) routing: OutputRoutingDefinition,
 }
 
 impl OutputDefinition {
@@ -17,11 +28,80 @@ impl OutputDefinition {
     pub fn enttec(&self) -> &EnttecDmxOutputDefinition {
         &self.enttec
     }
+
+    pub fn artnet(&self) -> &ArtNetDmxOutputDefinition {
+        &self.artnet
+    }
+
+    pub fn serial(&self) -> &SerialDmxOutputDefinition {
+        &self.serial
+    }
+
+    pub fn loopback(&self) -> &LoopbackOutputDefinition {
+        &self.loopback
+    }
+
+    pub fn routing(&self) -> &OutputRoutingDefinition {
+        &self.routing
+    }
+}
+
+/// The kinds of output instances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub enum OutputInstanceKind {
+    Sacn,
+    Enttec,
+    ArtNet,
+    Serial,
+    Loopback,
+}
+
+impl OutputInstanceKind {
+    pub const ALL: [Self; 5] =
+        [Self::Sacn, Self::Enttec, Self::ArtNet, Self::Serial, Self::Loopback];
+}
+
+impl fmt::Display for OutputInstanceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sacn => write!(f, "sACN"),
+            Self::Enttec => write!(f, "Enttec"),
+            Self::ArtNet => write!(f, "Art-Net"),
+            Self::Serial => write!(f, "serial"),
+            Self::Loopback => write!(f, "loopback"),
+        }
+    }
+}
+
+/// The definition of a single output instance of any kind.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind")]
+pub enum OutputInstanceDefinition {
+    Sacn(SacnDmxOutputInstanceDefinition),
+    Enttec(EnttecDmxOutputInstanceDefinition),
+    ArtNet(ArtNetDmxOutputInstanceDefinition),
+    Serial(SerialDmxOutputInstanceDefinition),
+    Loopback(LoopbackOutputInstanceDefinition),
+}
+
+impl OutputInstanceDefinition {
+    pub fn kind(&self) -> OutputInstanceKind {
+        match self {
+            Self::Sacn(_) => OutputInstanceKind::Sacn,
+            Self::Enttec(_) => OutputInstanceKind::Enttec,
+            Self::ArtNet(_) => OutputInstanceKind::ArtNet,
+            Self::Serial(_) => OutputInstanceKind::Serial,
+            Self::Loopback(_) => OutputInstanceKind::Loopback,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct SacnDmxOutputDefinition {
+    #[serde(deserialize_with = "SacnDmxOutputInstanceDefinition::deserialize_instances")]
     pub(crate) instances: Vec<SacnDmxOutputInstanceDefinition>,
 }
 
@@ -35,17 +115,71 @@ impl SacnDmxOutputDefinition {
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct SacnDmxOutputInstanceDefinition {
     pub(crate) name: String,
+    /// Identifies this instance to receivers across restarts. Generated when missing.
+    #[serde(default = "Uuid::new_v4")]
+    pub(crate) cid: Uuid,
     pub(crate) universe_ids: Vec<UniverseId>,
     pub(crate) preview_mode: bool,
     pub(crate) priority: u8,
-    pub(crate) target_address: SocketAddr,
+    /// Unicast destinations. When empty, each universe is multicast to 239.255.{hi}.{lo}.
+    #[serde(default)]
+    pub(crate) target_addresses: Vec<SocketAddr>,
+    /// The single destination of older projects, moved into `target_addresses` on load.
+    #[serde(default, skip_serializing)]
+    target_address: Option<SocketAddr>,
+    /// Local address of the network interface to send from.
+    #[serde(default)]
+    pub(crate) bind_address: Option<IpAddr>,
+    /// The universe sync packets are sent on. When set, receivers only output a frame
+    /// once all universes of this instance have arrived.
+    #[serde(default)]
+    pub(crate) synchronization_address: Option<u16>,
+    /// Sends per-address priority (start code 0xDD), so other consoles can control the
+    /// channels that aren't patched in this universe.
+    #[serde(default)]
+    pub(crate) per_address_priority: bool,
+    /// Frames per second.
+    #[serde(default = "SacnDmxOutputInstanceDefinition::default_refresh_rate")]
+    pub(crate) refresh_rate: f32,
+    /// Only sends universes when their data changed, and otherwise at the keepalive interval.
+    #[serde(default)]
+    pub(crate) send_on_change: bool,
+    #[serde(default = "SacnDmxOutputInstanceDefinition::default_keepalive_interval_ms")]
+    pub(crate) keepalive_interval_ms: u32,
 }
 
 impl SacnDmxOutputInstanceDefinition {
+    fn default_refresh_rate() -> f32 {
+        44.0
+    }
+
+    /// E1.31 recommends keepalives between 800 ms and 1 s.
+    fn default_keepalive_interval_ms() -> u32 {
+        1000
+    }
+
+    fn deserialize_instances<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Self>, D::Error> {
+        let mut instances = <Vec<Self> as serde::Deserialize>::deserialize(deserializer)?;
+        for instance in &mut instances {
+            if let Some(address) = instance.target_address.take()
+                && !instance.target_addresses.contains(&address)
+            {
+                instance.target_addresses.push(address);
+            }
+        }
+        Ok(instances)
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn cid(&self) -> Uuid {
+        self.cid
+    }
+
     pub fn universe_ids(&self) -> &[UniverseId] {
         &self.universe_ids
     }
@@ -58,8 +192,32 @@ impl SacnDmxOutputInstanceDefinition {
         self.priority
     }
 
-    pub fn target_address(&self) -> SocketAddr {
-        self.target_address
+    pub fn target_addresses(&self) -> &[SocketAddr] {
+        &self.target_addresses
+    }
+
+    pub fn bind_address(&self) -> Option<IpAddr> {
+        self.bind_address
+    }
+
+    pub fn synchronization_address(&self) -> Option<u16> {
+        self.synchronization_address
+    }
+
+    pub fn per_address_priority(&self) -> bool {
+        self.per_address_priority
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+
+    pub fn send_on_change(&self) -> bool {
+        self.send_on_change
+    }
+
+    pub fn keepalive_interval_ms(&self) -> u32 {
+        self.keepalive_interval_ms
     }
 }
 
@@ -79,5 +237,388 @@ impl EnttecDmxOutputDefinition {
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct EnttecDmxOutputInstanceDefinition {
     pub(crate) universe_id: UniverseId,
+    /// The FTDI serial number, used to open the device through the D2XX driver.
+    #[serde(default)]
     pub(crate) serial_number: String,
+    #[serde(default)]
+    pub(crate) kind: EnttecDeviceKind,
+    /// Path of the serial port of a DMX USB Pro, like `/dev/ttyUSB0`. When set, the widget
+    /// is opened as a plain serial port instead of through the D2XX driver.
+    #[serde(default)]
+    pub(crate) serial_port: Option<String>,
+    /// The input universe DMX received by a DMX USB Pro is exposed as.
+    #[serde(default)]
+    pub(crate) input_universe_id: Option<UniverseId>,
+    /// Frames per second.
+    #[serde(default = "EnttecDmxOutputInstanceDefinition::default_refresh_rate")]
+    pub(crate) refresh_rate: f32,
+}
+
+impl EnttecDmxOutputInstanceDefinition {
+    fn default_refresh_rate() -> f32 {
+        25.0
+    }
+
+    pub fn universe_id(&self) -> UniverseId {
+        self.universe_id
+    }
+
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    pub fn kind(&self) -> EnttecDeviceKind {
+        self.kind
+    }
+
+    pub fn serial_port(&self) -> Option<&str> {
+        self.serial_port.as_deref()
+    }
+
+    pub fn input_universe_id(&self) -> Option<UniverseId> {
+        self.input_universe_id
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub enum EnttecDeviceKind {
+    /// Open DMX style devices, where the host generates the DMX signal.
+    #[default]
+    OpenDmx,
+    /// DMX USB Pro widgets, driven through the widget API.
+    Pro,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ArtNetDmxOutputDefinition {
+    pub(crate) instances: Vec<ArtNetDmxOutputInstanceDefinition>,
+}
+
+impl ArtNetDmxOutputDefinition {
+    pub fn instances(&self) -> &[ArtNetDmxOutputInstanceDefinition] {
+        &self.instances
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ArtNetDmxOutputInstanceDefinition {
+    pub(crate) name: String,
+    /// Universe 1 is sent on Art-Net Port-Address 0.
+    pub(crate) universe_ids: Vec<UniverseId>,
+    /// Nodes to send to directly. Data is broadcast when this is empty.
+    #[serde(default)]
+    pub(crate) target_addresses: Vec<Ipv4Addr>,
+    #[serde(default = "ArtNetDmxOutputInstanceDefinition::default_broadcast_address")]
+    pub(crate) broadcast_address: Ipv4Addr,
+    /// Sends an ArtSync after every frame, so nodes output all universes at once.
+    #[serde(default)]
+    pub(crate) sync: bool,
+    /// Frames per second.
+    #[serde(default = "ArtNetDmxOutputInstanceDefinition::default_refresh_rate")]
+    pub(crate) refresh_rate: f32,
+    /// Only sends universes when their data changed, and otherwise at the keepalive interval.
+    #[serde(default)]
+    pub(crate) send_on_change: bool,
+    #[serde(default = "ArtNetDmxOutputInstanceDefinition::default_keepalive_interval_ms")]
+    pub(crate) keepalive_interval_ms: u32,
+}
+
+impl ArtNetDmxOutputInstanceDefinition {
+    fn default_broadcast_address() -> Ipv4Addr {
+        Ipv4Addr::BROADCAST
+    }
+
+    fn default_refresh_rate() -> f32 {
+        44.0
+    }
+
+    /// Art-Net nodes expect data at least every 4 seconds.
+    fn default_keepalive_interval_ms() -> u32 {
+        1000
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn universe_ids(&self) -> &[UniverseId] {
+        &self.universe_ids
+    }
+
+    pub fn target_addresses(&self) -> &[Ipv4Addr] {
+        &self.target_addresses
+    }
+
+    pub fn broadcast_address(&self) -> Ipv4Addr {
+        self.broadcast_address
+    }
+
+    pub fn sync(&self) -> bool {
+        self.sync
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+
+    pub fn send_on_change(&self) -> bool {
+        self.send_on_change
+    }
+
+    pub fn keepalive_interval_ms(&self) -> u32 {
+        self.keepalive_interval_ms
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SerialDmxOutputDefinition {
+    pub(crate) instances: Vec<SerialDmxOutputInstanceDefinition>,
+}
+
+impl SerialDmxOutputDefinition {
+    pub fn instances(&self) -> &[SerialDmxOutputInstanceDefinition] {
+        &self.instances
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SerialDmxOutputInstanceDefinition {
+    pub(crate) universe_id: UniverseId,
+    /// Path of the serial port, like `/dev/ttyUSB0`.
+    pub(crate) path: String,
+    #[serde(default)]
+    pub(crate) break_mode: SerialBreakMode,
+    #[serde(default = "SerialDmxOutputInstanceDefinition::default_break_time_us")]
+    pub(crate) break_time_us: u32,
+    #[serde(default = "SerialDmxOutputInstanceDefinition::default_mark_after_break_us")]
+    pub(crate) mark_after_break_us: u32,
+    /// Frames per second.
+    #[serde(default = "SerialDmxOutputInstanceDefinition::default_refresh_rate")]
+    pub(crate) refresh_rate: f32,
+}
+
+impl SerialDmxOutputInstanceDefinition {
+    fn default_break_time_us() -> u32 {
+        176
+    }
+
+    fn default_mark_after_break_us() -> u32 {
+        16
+    }
+
+    fn default_refresh_rate() -> f32 {
+        40.0
+    }
+
+    pub fn universe_id(&self) -> UniverseId {
+        self.universe_id
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn break_mode(&self) -> SerialBreakMode {
+        self.break_mode
+    }
+
+    pub fn break_time_us(&self) -> u32 {
+        self.break_time_us
+    }
+
+    pub fn mark_after_break_us(&self) -> u32 {
+        self.mark_after_break_us
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub enum SerialBreakMode {
+    /// Generates the break with the break ioctl of the serial driver.
+    #[default]
+    Ioctl,
+    /// Generates the break by sending a null byte at a lower baud rate.
+    BaudRate,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct LoopbackOutputDefinition {
+    pub(crate) instances: Vec<LoopbackOutputInstanceDefinition>,
+}
+
+impl LoopbackOutputDefinition {
+    pub fn instances(&self) -> &[LoopbackOutputInstanceDefinition] {
+        &self.instances
+    }
+}
+
+/// An instance that hands its frames to in-process subscribers, like tests or a monitor.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct LoopbackOutputInstanceDefinition {
+    pub(crate) name: String,
+    /// The universes to publish. When empty, all universes are published.
+    #[serde(default)]
+    pub(crate) universe_ids: Vec<UniverseId>,
+    /// Frames per second.
+    #[serde(default = "LoopbackOutputInstanceDefinition::default_refresh_rate")]
+    pub(crate) refresh_rate: f32,
+}
+
+impl LoopbackOutputInstanceDefinition {
+    fn default_refresh_rate() -> f32 {
+        44.0
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn universe_ids(&self) -> &[UniverseId] {
+        &self.universe_ids
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+}
+
+/// Maps the internal universes to the universes the output instances send.
+///
+/// Universes that aren't the source of any route are sent unchanged.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OutputRoutingDefinition {
+    #[serde(default)]
+    pub(crate) routes: Vec<OutputRouteDefinition>,
+    #[serde(default)]
+    pub(crate) limits: Vec<OutputChannelLimitDefinition>,
+}
+
+impl OutputRoutingDefinition {
+    pub fn routes(&self) -> &[OutputRouteDefinition] {
+        &self.routes
+    }
+
+    pub fn limits(&self) -> &[OutputChannelLimitDefinition] {
+        &self.limits
+    }
+}
+
+/// Copies a range of channels from an internal universe to an output universe.
+///
+/// Routes into the same output channels are merged HTP.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OutputRouteDefinition {
+    pub(crate) source_universe_id: UniverseId,
+    #[serde(default)]
+    pub(crate) source_channel: Channel,
+    pub(crate) target_universe_id: UniverseId,
+    #[serde(default)]
+    pub(crate) target_channel: Channel,
+    /// The number of channels to copy. Channels past the end of either universe are dropped.
+    #[serde(default = "OutputRouteDefinition::default_count")]
+    pub(crate) count: u16,
+}
+
+impl OutputRouteDefinition {
+    fn default_count() -> u16 {
+        Channel::MAX.as_u16()
+    }
+
+    pub fn source_universe_id(&self) -> UniverseId {
+        self.source_universe_id
+    }
+
+    pub fn source_channel(&self) -> Channel {
+        self.source_channel
+    }
+
+    pub fn target_universe_id(&self) -> UniverseId {
+        self.target_universe_id
+    }
+
+    pub fn target_channel(&self) -> Channel {
+        self.target_channel
+    }
+
+    pub fn count(&self) -> u16 {
+        self.count
+    }
+}
+
+/// Caps the values of a range of channels in an output universe.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OutputChannelLimitDefinition {
+    pub(crate) universe_id: UniverseId,
+    pub(crate) channel: Channel,
+    #[serde(default = "OutputChannelLimitDefinition::default_count")]
+    pub(crate) count: u16,
+    /// The highest value sent. A limit of 0 inhibits the channels.
+    pub(crate) limit: u8,
+}
+
+impl OutputChannelLimitDefinition {
+    fn default_count() -> u16 {
+        1
+    }
+
+    pub fn universe_id(&self) -> UniverseId {
+        self.universe_id
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub fn count(&self) -> u16 {
+        self.count
+    }
+
+    pub fn limit(&self) -> u8 {
+        self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn sacn_legacy_target_address() {
+        let definition: SacnDmxOutputDefinition = serde_json::from_value(json!({
+            "instances": [{
+                "name": "Legacy",
+                "universe_ids": [1],
+                "preview_mode": false,
+                "priority": 100,
+                "target_address": "10.0.0.5:5568",
+            }],
+        }))
+        .unwrap();
+
+        let instance = &definition.instances()[0];
+        assert_eq!(instance.target_addresses(), ["10.0.0.5:5568".parse().unwrap()]);
+        let value = serde_json::to_value(&definition).unwrap();
+        assert_eq!(value["instances"][0]["target_addresses"], json!(["10.0.0.5:5568"]));
+        assert!(value["instances"][0].get("target_address").is_none());
+    }
 }
@@ -0,0 +1,224 @@
+use std::net::Ipv4Addr;
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::Context as _;
+
+use crate::{
+    dmx::{Multiverse, UniverseId},
+    output::{
+        ArtNetDmxOutputInstanceDefinition,
+        instance::timing::{self, SendFilter},
+        protocol::artnet::{
+            ArtPollReply, MAX_PORT_ADDRESS, PortAddress,
+            controller::{Controller, Destination},
+        },
+        status::InstanceHealth,
+    },
+};
+
+pub struct ArtNetInstance {
+    name: String,
+    universe_ids: Vec<UniverseId>,
+    target_addresses: Vec<Ipv4Addr>,
+    broadcast_address: Ipv4Addr,
+    sync: bool,
+    refresh_rate: f32,
+    send_on_change: bool,
+    keepalive_interval: Duration,
+    nodes: Arc<RwLock<Vec<ArtPollReply>>>,
+
+    health: Arc<InstanceHealth>,
+
+    thread_handle: Option<JoinHandle<()>>,
+    thread_running: Arc<AtomicBool>,
+}
+
+impl ArtNetInstance {
+    pub fn new(definition: ArtNetDmxOutputInstanceDefinition) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: definition.name,
+            universe_ids: definition.universe_ids,
+            target_addresses: definition.target_addresses,
+            broadcast_address: definition.broadcast_address,
+            sync: definition.sync,
+            refresh_rate: definition.refresh_rate,
+            send_on_change: definition.send_on_change,
+            keepalive_interval: Duration::from_millis(definition.keepalive_interval_ms.into()),
+            nodes: Arc::new(RwLock::new(Vec::new())),
+
+            health: Arc::new(InstanceHealth::default()),
+
+            thread_handle: None,
+            thread_running: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn start(&mut self, multiverse: Arc<RwLock<Multiverse>>) -> anyhow::Result<()> {
+        if self.thread_handle.is_some() {
+            log::warn!("Art-Net instance '{}' thread already running", self.name);
+            return Ok(());
+        }
+
+        let destination = if self.target_addresses.is_empty() {
+            Destination::Broadcast(self.broadcast_address)
+        } else {
+            Destination::Unicast(self.target_addresses.clone())
+        };
+        let mut controller = Controller::new(destination, self.broadcast_address)
+            .context("Failed to create Art-Net controller")?;
+
+        let interval = timing::refresh_interval(self.refresh_rate)?;
+        let mut send_filter =
+            SendFilter::new(self.send_on_change.then_some(self.keepalive_interval));
+
+        let universe_ids = self.universe_ids.clone();
+        let sync = self.sync;
+        let nodes = self.nodes.clone();
+        let running = self.thread_running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let name = self.name.clone();
+        let health = self.health.clone();
+        let handle = thread::Builder::new()
+            .name(format!("rd_artnet_{}", self.name))
+            .spawn(move || {
+                timing::run_at_interval(&running, interval, || {
+                    let frame = multiverse.read().unwrap().clone();
+
+                    match handle_frame(
+                        &mut controller,
+                        &universe_ids,
+                        sync,
+                        &mut send_filter,
+                        frame,
+                    ) {
+                        Ok(()) => health.frame_sent(),
+                        Err(err) => {
+                            log::error!("Art-Net instance '{name}' failed to send frame: {err}");
+                            health.error(err);
+                        }
+                    }
+
+                    match controller.poll() {
+                        Ok(()) => update_nodes(&controller, &nodes),
+                        Err(err) => log::warn!("Art-Net instance '{name}' failed to poll: {err}"),
+                    }
+                });
+            })
+            .context("Failed to spawn Art-Net instance thread")?;
+
+        self.thread_handle = Some(handle);
+        self.health.started();
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if self.thread_handle.is_some() {
+            self.thread_running.store(false, Ordering::SeqCst);
+            if let Some(handle) = self.thread_handle.take() {
+                let _ = handle.join();
+            }
+        }
+        self.health.stopped();
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn universe_ids(&self) -> &[UniverseId] {
+        &self.universe_ids
+    }
+
+    pub fn target_addresses(&self) -> &[Ipv4Addr] {
+        &self.target_addresses
+    }
+
+    pub fn broadcast_address(&self) -> Ipv4Addr {
+        self.broadcast_address
+    }
+
+    pub fn sync(&self) -> bool {
+        self.sync
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+
+    pub fn send_on_change(&self) -> bool {
+        self.send_on_change
+    }
+
+    pub fn keepalive_interval(&self) -> Duration {
+        self.keepalive_interval
+    }
+
+    pub(crate) fn health(&self) -> &InstanceHealth {
+        &self.health
+    }
+
+    /// The nodes that answered the most recent polls.
+    pub fn nodes(&self) -> Vec<ArtPollReply> {
+        self.nodes.read().unwrap().clone()
+    }
+}
+
+fn handle_frame(
+    controller: &mut Controller,
+    universe_ids: &[UniverseId],
+    sync: bool,
+    send_filter: &mut SendFilter,
+    frame: Multiverse,
+) -> anyhow::Result<()> {
+    let mut sent = false;
+    for universe_id in universe_ids {
+        let Some(universe) = frame.universe(universe_id) else {
+            continue;
+        };
+
+        if !send_filter.should_send(*universe_id, universe) {
+            continue;
+        }
+
+        let Some(port_address) = port_address(*universe_id) else {
+            log::warn!("Universe {universe_id} can't be sent over Art-Net");
+            continue;
+        };
+
+        let data = universe.values().map(|v| v.as_u8()).to_vec();
+        controller.send_dmx(port_address, data)?;
+        sent = true;
+    }
+
+    if sync && sent {
+        controller.send_sync()?;
+    }
+
+    Ok(())
+}
+
+fn update_nodes(controller: &Controller, nodes: &RwLock<Vec<ArtPollReply>>) {
+    let mut current = controller.nodes().cloned().collect::<Vec<_>>();
+    current.sort_by_key(|node| node.ip);
+
+    let mut nodes = nodes.write().unwrap();
+    if *nodes != current {
+        log::info!(
+            "Art-Net nodes: {:?}",
+            current.iter().map(|n| &n.short_name).collect::<Vec<_>>()
+        );
+        *nodes = current;
+    }
+}
+
+fn port_address(universe_id: UniverseId) -> Option<PortAddress> {
+    let port_address = universe_id.as_u16() - 1;
+    (port_address <= MAX_PORT_ADDRESS).then_some(port_address)
+}
@@ -1,15 +1,13 @@
+use std::io;
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
-use std::{
-    sync::{
-        Arc, RwLock,
-        atomic::{AtomicBool, Ordering},
-    },
-    time::Instant,
-};
 
 use anyhow::Context as _;
-use libftd2xx::{BitsPerWord, Ftdi, FtdiCommon, Parity, StopBits, TimeoutError};
+use libftd2xx::{BitsPerWord, FtStatus, Ftdi, FtdiCommon, Parity, StopBits, TimeoutError};
 use thread_priority::ThreadBuilderExt;
 
 const BAUDRATE: u32 = 250000;
@@ -18,17 +16,41 @@ const STOP_BITS_2: StopBits = StopBits::Bits2;
 const PARITY_NONE: Parity = Parity::No;
 const READ_TIMEOUT: Duration = Duration::from_millis(1000);
 const WRITE_TIMEOUT: Duration = Duration::from_millis(1000);
-
-const INTERVAL: Duration = Duration::from_millis(40);
+/// Keeps the widget loop responsive when no data is received.
+const PRO_READ_TIMEOUT: Duration = Duration::from_millis(5);
 
 use crate::{
-    dmx::{Multiverse, UniverseId},
-    output::EnttecDmxOutputInstanceDefinition,
+    dmx::{self, Multiverse, UniverseId},
+    output::{
+        EnttecDeviceKind, EnttecDmxOutputInstanceDefinition,
+        instance::timing,
+        protocol::enttec::{
+            self, Message, ReceivedDmx, SerialNumber, WidgetParameters, label,
+            widget::{Port, Widget},
+        },
+        status::InstanceHealth,
+    },
 };
 
+/// The body of the instance thread, run until the flag is cleared.
+type InstanceThread = Box<dyn FnOnce(Arc<AtomicBool>, Duration) + Send>;
+
+/// What a DMX USB Pro widget reported about itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EnttecWidgetInfo {
+    pub parameters: Option<WidgetParameters>,
+    pub serial_number: Option<SerialNumber>,
+}
+
 pub struct EnttecInstance {
     universe_id: UniverseId,
     serial_number: String,
+    kind: EnttecDeviceKind,
+    serial_port: Option<String>,
+    input_universe_id: Option<UniverseId>,
+    refresh_rate: f32,
+    widget_info: Arc<RwLock<EnttecWidgetInfo>>,
+    health: Arc<InstanceHealth>,
 
     thread_handle: Option<JoinHandle<()>>,
     thread_running: Arc<AtomicBool>,
@@ -39,6 +61,12 @@ impl EnttecInstance {
         Ok(Self {
             universe_id: definition.universe_id,
             serial_number: definition.serial_number,
+            kind: definition.kind,
+            serial_port: definition.serial_port,
+            input_universe_id: definition.input_universe_id,
+            refresh_rate: definition.refresh_rate,
+            widget_info: Arc::new(RwLock::new(EnttecWidgetInfo::default())),
+            health: Arc::new(InstanceHealth::default()),
 
             thread_handle: None,
             thread_running: Arc::new(AtomicBool::new(false)),
@@ -47,27 +75,25 @@ impl EnttecInstance {
 
     pub fn start(
         &mut self,
-        _notify_rx: flume::Receiver<()>,
         multiverse: Arc<RwLock<Multiverse>>,
+        input_multiverse: Arc<RwLock<Multiverse>>,
     ) -> anyhow::Result<()> {
         if self.thread_handle.is_some() {
-            log::warn!("Enttec instance '{}' thread already running", self.serial_number);
+            log::warn!("Enttec instance '{}' thread already running", self.name());
             return Ok(());
         }
 
-        let mut ftdi = Ftdi::with_serial_number(&self.serial_number).with_context(|| {
-            format!("Failed to open FTDI device, possible devices: {:?}", libftd2xx::list_devices())
-        })?;
+        let interval = timing::refresh_interval(self.refresh_rate)?;
+        let thread = match self.kind {
+            EnttecDeviceKind::OpenDmx => self.open_dmx_thread(multiverse)?,
+            EnttecDeviceKind::Pro => self.pro_thread(multiverse, input_multiverse)?,
+        };
 
-        ftdi_init(&mut ftdi).context("Failed to initialize FTDI device")?;
+        self.thread_running.store(true, Ordering::SeqCst);
 
-        let universe_id = self.universe_id.clone();
         let running = self.thread_running.clone();
-        running.store(true, Ordering::SeqCst);
-
-        let serial = self.serial_number.clone();
         let handle = thread::Builder::new()
-            .name(format!("enttec_open_dmx_{}", self.serial_number))
+            .name(format!("enttec_{}", self.name()))
             .spawn_with_priority(thread_priority::ThreadPriority::Max, move |prio_result| {
                 if prio_result.is_err() {
                     log::warn!(
@@ -76,39 +102,12 @@ impl EnttecInstance {
                     );
                 }
 
-                let sleeper = spin_sleep::SpinSleeper::default();
-                let mut next_tick = Instant::now() + INTERVAL;
-                while running.load(Ordering::SeqCst) {
-                    let now = Instant::now();
-
-                    let frame = multiverse.read().unwrap().clone();
-
-                    if now < next_tick {
-                        sleeper.sleep_until(next_tick);
-                    } else {
-                        let deviation = (now - next_tick).as_secs_f64();
-                        if now > next_tick + INTERVAL {
-                            // If we are more than one tick late, skip ahead to catch up.
-                            let ticks_missed =
-                                (deviation / INTERVAL.as_secs_f64()).floor() as u32 + 1;
-                            next_tick += INTERVAL * ticks_missed;
-                        }
-                    }
-
-                    if let Err(err) = handle_frame(&mut ftdi, &universe_id, frame) {
-                        log::error!("Enttec instance '{serial}' failed to send frame: {err}");
-                    }
-
-                    next_tick += INTERVAL;
-                }
-
-                if let Err(err) = ftdi_close(&mut ftdi) {
-                    log::error!("Enttec instance '{serial}' failed to shut down cleanly: {err}");
-                }
+                thread(running, interval);
             })
             .context("Failed to spawn Enttec instance thread")?;
 
         self.thread_handle = Some(handle);
+        self.health.started();
 
         Ok(())
     }
@@ -120,6 +119,12 @@ impl EnttecInstance {
                 let _ = handle.join();
             }
         }
+        self.health.stopped();
+    }
+
+    /// The serial port path, or the FTDI serial number if the device isn't opened as a serial port.
+    pub fn name(&self) -> &str {
+        self.serial_port.as_deref().unwrap_or(&self.serial_number)
     }
 
     pub fn universe_id(&self) -> UniverseId {
@@ -129,6 +134,140 @@ impl EnttecInstance {
     pub fn serial_number(&self) -> &str {
         &self.serial_number
     }
+
+    pub fn kind(&self) -> EnttecDeviceKind {
+        self.kind
+    }
+
+    pub fn serial_port(&self) -> Option<&str> {
+        self.serial_port.as_deref()
+    }
+
+    pub fn input_universe_id(&self) -> Option<UniverseId> {
+        self.input_universe_id
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+
+    /// What a DMX USB Pro widget reported about itself since it was started.
+    pub fn widget_info(&self) -> EnttecWidgetInfo {
+        *self.widget_info.read().unwrap()
+    }
+
+    pub(crate) fn health(&self) -> &InstanceHealth {
+        &self.health
+    }
+
+    fn open_dmx_thread(
+        &self,
+        multiverse: Arc<RwLock<Multiverse>>,
+    ) -> anyhow::Result<InstanceThread> {
+        let mut ftdi = Ftdi::with_serial_number(&self.serial_number).with_context(|| {
+            format!("Failed to open FTDI device, possible devices: {:?}", libftd2xx::list_devices())
+        })?;
+
+        ftdi_init(&mut ftdi).context("Failed to initialize FTDI device")?;
+
+        let universe_id = self.universe_id;
+        let serial = self.serial_number.clone();
+        let health = self.health.clone();
+        Ok(Box::new(move |running: Arc<AtomicBool>, interval| {
+            timing::run_at_interval(&running, interval, || {
+                let frame = multiverse.read().unwrap().clone();
+                match handle_frame(&mut ftdi, &universe_id, frame) {
+                    Ok(()) => health.frame_sent(),
+                    Err(err) => {
+                        log::error!("Enttec instance '{serial}' failed to send frame: {err}");
+                        health.error(err);
+                    }
+                }
+            });
+
+            if let Err(err) = ftdi_close(&mut ftdi) {
+                log::error!("Enttec instance '{serial}' failed to shut down cleanly: {err}");
+            }
+        }))
+    }
+
+    fn pro_thread(
+        &self,
+        multiverse: Arc<RwLock<Multiverse>>,
+        input_multiverse: Arc<RwLock<Multiverse>>,
+    ) -> anyhow::Result<InstanceThread> {
+        let port: Box<dyn Port> = match &self.serial_port {
+            Some(path) => Box::new(
+                serialport::new(path, enttec::BAUD_RATE)
+                    .timeout(PRO_READ_TIMEOUT)
+                    .open()
+                    .with_context(|| format!("Failed to open serial port '{path}'"))?,
+            ),
+            None => {
+                let mut ftdi =
+                    Ftdi::with_serial_number(&self.serial_number).with_context(|| {
+                        format!(
+                            "Failed to open FTDI device, possible devices: {:?}",
+                            libftd2xx::list_devices()
+                        )
+                    })?;
+                ftdi_pro_init(&mut ftdi).context("Failed to initialize FTDI device")?;
+                Box::new(FtdiPort(ftdi))
+            }
+        };
+
+        let mut widget = Widget::new(port);
+        widget.send(&Message::get_widget_parameters())?;
+        widget.send(&Message::get_widget_serial_number())?;
+        if self.input_universe_id.is_some() {
+            widget.send(&Message::receive_dmx_on_change(false))?;
+        }
+
+        let universe_id = self.universe_id;
+        let input_universe_id = self.input_universe_id;
+        let widget_info = self.widget_info.clone();
+        let name = self.name().to_string();
+        let health = self.health.clone();
+        Ok(Box::new(move |running: Arc<AtomicBool>, interval| {
+            timing::run_at_interval(&running, interval, || {
+                let frame = multiverse.read().unwrap().clone();
+                if let Some(universe) = frame.universe(&universe_id) {
+                    let data = universe.values().map(|v| v.as_u8());
+                    match widget.send_dmx(&data) {
+                        Ok(()) => health.frame_sent(),
+                        Err(err) => {
+                            log::error!("Enttec instance '{name}' failed to send frame: {err}");
+                            health.error(err);
+                        }
+                    }
+                }
+
+                let messages = match widget.receive() {
+                    Ok(messages) => messages,
+                    Err(err) => {
+                        log::error!("Enttec instance '{name}' failed to receive: {err}");
+                        health.error(err);
+                        return;
+                    }
+                };
+
+                for message in messages {
+                    handle_pro_message(
+                        &message,
+                        input_universe_id,
+                        &input_multiverse,
+                        &widget_info,
+                    )
+                    .map_err(|err| log::warn!("Enttec instance '{name}': {err}"))
+                    .ok();
+                }
+            });
+
+            if let Some(input_universe_id) = input_universe_id {
+                input_multiverse.write().unwrap().remove_universe(&input_universe_id);
+            }
+        }))
+    }
 }
 
 fn handle_frame(
@@ -147,6 +286,48 @@ fn handle_frame(
     Ok(())
 }
 
+fn handle_pro_message(
+    message: &Message,
+    input_universe_id: Option<UniverseId>,
+    input_multiverse: &RwLock<Multiverse>,
+    widget_info: &RwLock<EnttecWidgetInfo>,
+) -> anyhow::Result<()> {
+    match message.label {
+        label::GET_WIDGET_PARAMETERS => {
+            let parameters = WidgetParameters::decode(message)?;
+            log::info!("Enttec DMX USB Pro firmware version {:#06x}", parameters.firmware_version);
+            widget_info.write().unwrap().parameters = Some(parameters);
+        }
+        label::GET_WIDGET_SERIAL_NUMBER => {
+            let serial_number = SerialNumber::decode(message)?;
+            log::info!("Enttec DMX USB Pro serial number {serial_number}");
+            widget_info.write().unwrap().serial_number = Some(serial_number);
+        }
+        label::RECEIVED_DMX => {
+            let Some(input_universe_id) = input_universe_id else {
+                return Ok(());
+            };
+
+            let received = ReceivedDmx::decode(message)?;
+            if received.queue_overflow() {
+                log::warn!("Enttec DMX USB Pro dropped received frames");
+            }
+            if received.overrun() || received.start_code != dmx::NULL_START_CODE {
+                return Ok(());
+            }
+
+            let mut universe = dmx::Universe::new();
+            for (value, slot) in universe.values_mut().iter_mut().zip(received.data) {
+                *value = dmx::Value(slot);
+            }
+            input_multiverse.write().unwrap().create_universe(input_universe_id, universe);
+        }
+        other => log::debug!("Ignoring Enttec DMX USB Pro message with label {other}"),
+    }
+
+    Ok(())
+}
+
 fn ftdi_init(ftdi: &mut Ftdi) -> anyhow::Result<()> {
     ftdi.reset()?;
     ftdi.set_baud_rate(BAUDRATE)?;
@@ -159,6 +340,17 @@ fn ftdi_init(ftdi: &mut Ftdi) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn ftdi_pro_init(ftdi: &mut Ftdi) -> anyhow::Result<()> {
+    ftdi.reset()?;
+    ftdi.set_baud_rate(enttec::BAUD_RATE)?;
+    ftdi.set_data_characteristics(BITS_8, StopBits::Bits1, PARITY_NONE)?;
+    ftdi.set_timeouts(READ_TIMEOUT, WRITE_TIMEOUT)?;
+    ftdi.set_flow_control_none()?;
+    ftdi.purge_rx()?;
+    ftdi.purge_tx()?;
+    Ok(())
+}
+
 fn ftdi_send(ftdi: &mut Ftdi, buffer: &[u8]) -> anyhow::Result<()> {
     ftdi.set_break_on()?;
     ftdi.set_break_off()?;
@@ -174,3 +366,32 @@ fn ftdi_close(ftdi: &mut Ftdi) -> anyhow::Result<()> {
     ftdi.close()?;
     Ok(())
 }
+
+/// Exposes an FTDI device as a byte stream to a DMX USB Pro widget.
+struct FtdiPort(Ftdi);
+
+impl io::Read for FtdiPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Only read what's queued, so reading doesn't block until the read timeout.
+        let queued = self.0.queue_status().map_err(ftdi_io_error)?;
+        if queued == 0 {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let len = queued.min(buf.len());
+        self.0.read(&mut buf[..len]).map_err(ftdi_io_error)
+    }
+}
+
+impl io::Write for FtdiPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf).map_err(ftdi_io_error)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn ftdi_io_error(status: FtStatus) -> io::Error {
+    io::Error::other(format!("FTDI error: {status:?}"))
+}
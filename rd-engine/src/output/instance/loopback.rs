@@ -0,0 +1,129 @@
+use std::sync::{
+    Arc, Mutex, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread::{self, JoinHandle};
+
+use anyhow::Context as _;
+
+use crate::{
+    dmx::{Multiverse, UniverseId},
+    output::{LoopbackOutputInstanceDefinition, instance::timing, status::InstanceHealth},
+};
+
+/// The number of frames queued for a subscriber. Frames are dropped for subscribers that
+/// fall further behind.
+const QUEUE_SIZE: usize = 64;
+
+/// Publishes the frames it would send to in-process subscribers, instead of to a device
+/// or the network.
+pub struct LoopbackInstance {
+    name: String,
+    universe_ids: Vec<UniverseId>,
+    refresh_rate: f32,
+
+    subscribers: Arc<Mutex<Vec<flume::Sender<Multiverse>>>>,
+    health: Arc<InstanceHealth>,
+
+    thread_handle: Option<JoinHandle<()>>,
+    thread_running: Arc<AtomicBool>,
+}
+
+impl LoopbackInstance {
+    pub fn new(definition: LoopbackOutputInstanceDefinition) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: definition.name,
+            universe_ids: definition.universe_ids,
+            refresh_rate: definition.refresh_rate,
+
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            health: Arc::new(InstanceHealth::default()),
+
+            thread_handle: None,
+            thread_running: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn start(&mut self, multiverse: Arc<RwLock<Multiverse>>) -> anyhow::Result<()> {
+        if self.thread_handle.is_some() {
+            log::warn!("Loopback instance '{}' thread already running", self.name);
+            return Ok(());
+        }
+
+        let interval = timing::refresh_interval(self.refresh_rate)?;
+
+        let universe_ids = self.universe_ids.clone();
+        let subscribers = self.subscribers.clone();
+        let health = self.health.clone();
+        let running = self.thread_running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let handle = thread::Builder::new()
+            .name(format!("rd_loopback_{}", self.name))
+            .spawn(move || {
+                timing::run_at_interval(&running, interval, || {
+                    let frame = filter_universes(&multiverse.read().unwrap(), &universe_ids);
+                    subscribers.lock().unwrap().retain(|tx| match tx.try_send(frame.clone()) {
+                        Ok(()) | Err(flume::TrySendError::Full(_)) => true,
+                        Err(flume::TrySendError::Disconnected(_)) => false,
+                    });
+                    health.frame_sent();
+                });
+            })
+            .context("Failed to spawn loopback instance thread")?;
+
+        self.thread_handle = Some(handle);
+        self.health.started();
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if self.thread_handle.is_some() {
+            self.thread_running.store(false, Ordering::SeqCst);
+            if let Some(handle) = self.thread_handle.take() {
+                let _ = handle.join();
+            }
+        }
+        self.health.stopped();
+    }
+
+    /// Receives every frame this instance sends, at its refresh rate.
+    ///
+    /// The subscription ends when the instance is edited or removed.
+    pub fn subscribe(&self) -> flume::Receiver<Multiverse> {
+        let (tx, rx) = flume::bounded(QUEUE_SIZE);
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn universe_ids(&self) -> &[UniverseId] {
+        &self.universe_ids
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+
+    pub(crate) fn health(&self) -> &InstanceHealth {
+        &self.health
+    }
+}
+
+fn filter_universes(multiverse: &Multiverse, universe_ids: &[UniverseId]) -> Multiverse {
+    if universe_ids.is_empty() {
+        return multiverse.clone();
+    }
+
+    let mut frame = Multiverse::new();
+    for id in universe_ids {
+        if let Some(universe) = multiverse.universe(id) {
+            frame.create_universe(*id, universe.clone());
+        }
+    }
+    frame
+}
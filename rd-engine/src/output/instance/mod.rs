@@ -1,2 +1,6 @@
+pub mod artnet;
 pub mod enttec;
+pub mod loopback;
 pub mod sacn;
+pub mod serial;
+pub(crate) mod timing;
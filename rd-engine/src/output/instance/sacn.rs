@@ -1,27 +1,43 @@
-use std::net::SocketAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{
     Arc, RwLock,
     atomic::{AtomicBool, Ordering},
 };
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use anyhow::Context as _;
 use uuid::Uuid;
 
 use crate::{
-    dmx::{Multiverse, UniverseId},
+    dmx::{Address, Multiverse, UniverseId},
     output::{
         SacnDmxOutputInstanceDefinition,
-        protocol::sacn::{self, Universe},
+        instance::timing::{self, SendFilter},
+        protocol::sacn::{self, MAX_UNIVERSE_SIZE, PER_ADDRESS_PRIORITY_START_CODE, Universe},
+        status::InstanceHealth,
     },
 };
 
+/// Receivers time out per-address priority after 2.5 seconds, so it's repeated well within that.
+const PER_ADDRESS_PRIORITY_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct SacnInstance {
     name: String,
+    cid: Uuid,
     universe_ids: Vec<UniverseId>,
     preview_mode: bool,
     priority: u8,
-    target_address: SocketAddr,
+    target_addresses: Vec<SocketAddr>,
+    bind_address: Option<IpAddr>,
+    synchronization_address: Option<u16>,
+    per_address_priority: bool,
+    refresh_rate: f32,
+    send_on_change: bool,
+    keepalive_interval: Duration,
+
+    health: Arc<InstanceHealth>,
 
     thread_handle: Option<JoinHandle<()>>,
     thread_running: Arc<AtomicBool>,
@@ -31,10 +47,19 @@ impl SacnInstance {
     pub fn new(definition: SacnDmxOutputInstanceDefinition) -> anyhow::Result<Self> {
         Ok(Self {
             name: definition.name,
+            cid: definition.cid,
             universe_ids: definition.universe_ids,
             preview_mode: definition.preview_mode,
             priority: definition.priority,
-            target_address: definition.target_address,
+            target_addresses: definition.target_addresses,
+            bind_address: definition.bind_address,
+            synchronization_address: definition.synchronization_address,
+            per_address_priority: definition.per_address_priority,
+            refresh_rate: definition.refresh_rate,
+            send_on_change: definition.send_on_change,
+            keepalive_interval: Duration::from_millis(definition.keepalive_interval_ms.into()),
+
+            health: Arc::new(InstanceHealth::default()),
 
             thread_handle: None,
             thread_running: Arc::new(AtomicBool::new(false)),
@@ -43,43 +68,69 @@ impl SacnInstance {
 
     pub fn start(
         &mut self,
-        notify_rx: flume::Receiver<()>,
         multiverse: Arc<RwLock<Multiverse>>,
+        patched_addresses: &HashSet<Address>,
     ) -> anyhow::Result<()> {
         if self.thread_handle.is_some() {
             log::warn!("sACN instance '{}' thread already running", self.name);
             return Ok(());
         }
 
-        let ip = self.target_address.ip();
-        let port = self.target_address.port();
-
         let mut sacn_source = sacn::source::Source::new(sacn::source::SourceConfig {
-            // FIXME: We should find a way to make this unique for each device, without it changing over time.
-            cid: Uuid::new_v4(),
+            cid: self.cid,
             name: self.name.to_owned(),
-            // FIXME: Implement multicasting.
-            ip,
-            port,
+            destinations: self.target_addresses.clone(),
+            port: sacn::DEFAULT_PORT,
+            bind_address: self.bind_address,
             priority: self.priority,
             preview_data: self.preview_mode,
-            synchronization_address: 0,
+            synchronization_address: self.synchronization_address.unwrap_or_default(),
             force_synchronization: false,
         })?;
 
+        let interval = timing::refresh_interval(self.refresh_rate)?;
+        let mut send_filter =
+            SendFilter::new(self.send_on_change.then_some(self.keepalive_interval));
+
         let universe_ids = self.universe_ids.clone();
+        let priorities = self
+            .per_address_priority
+            .then(|| per_address_priorities(&universe_ids, patched_addresses, self.priority));
         let running = self.thread_running.clone();
         running.store(true, Ordering::SeqCst);
 
         let name = self.name.clone();
+        let health = self.health.clone();
         let handle = thread::Builder::new()
             .name(format!("rd_sacn_{}", self.name))
             .spawn(move || {
-                while running.load(Ordering::SeqCst) && notify_rx.recv().is_ok() {
+                let mut last_priority_time: Option<Instant> = None;
+                timing::run_at_interval(&running, interval, || {
+                    if let Some(priorities) = &priorities
+                        && last_priority_time
+                            .is_none_or(|time| time.elapsed() >= PER_ADDRESS_PRIORITY_INTERVAL)
+                    {
+                        if let Err(err) = send_priorities(&sacn_source, priorities) {
+                            log::error!("sACN instance '{name}' failed to send priorities: {err}");
+                        }
+                        last_priority_time = Some(Instant::now());
+                    }
+
                     let frame = multiverse.read().unwrap().clone();
 
-                    if let Err(err) = handle_frame(&mut sacn_source, &universe_ids, frame) {
-                        log::error!("sACN instance '{name}' failed to send frame: {err}");
+                    match handle_frame(&mut sacn_source, &universe_ids, &mut send_filter, frame) {
+                        Ok(()) => health.frame_sent(),
+                        Err(err) => {
+                            log::error!("sACN instance '{name}' failed to send frame: {err}");
+                            health.error(err);
+                        }
+                    }
+                });
+
+                for universe_id in &universe_ids {
+                    let universe = Universe::new(universe_id.as_u16());
+                    if let Err(err) = sacn_source.terminate_universe(universe) {
+                        log::warn!("sACN instance '{name}' failed to terminate universe: {err}");
                     }
                 }
 
@@ -90,6 +141,9 @@ impl SacnInstance {
             .context("Failed to spawn sACN instance thread")?;
 
         self.thread_handle = Some(handle);
+        self.health.started();
+
+        log::info!("sACN instance '{}' started with CID {}", self.name, self.cid);
 
         Ok(())
     }
@@ -101,12 +155,17 @@ impl SacnInstance {
                 let _ = handle.join();
             }
         }
+        self.health.stopped();
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn cid(&self) -> Uuid {
+        self.cid
+    }
+
     pub fn universe_ids(&self) -> &[UniverseId] {
         &self.universe_ids
     }
@@ -119,28 +178,100 @@ impl SacnInstance {
         self.priority
     }
 
-    pub fn target_address(&self) -> SocketAddr {
-        self.target_address
+    pub fn target_addresses(&self) -> &[SocketAddr] {
+        &self.target_addresses
+    }
+
+    pub fn bind_address(&self) -> Option<IpAddr> {
+        self.bind_address
+    }
+
+    pub fn synchronization_address(&self) -> Option<u16> {
+        self.synchronization_address
+    }
+
+    pub fn per_address_priority(&self) -> bool {
+        self.per_address_priority
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+
+    pub fn send_on_change(&self) -> bool {
+        self.send_on_change
+    }
+
+    pub fn keepalive_interval(&self) -> Duration {
+        self.keepalive_interval
+    }
+
+    pub(crate) fn health(&self) -> &InstanceHealth {
+        &self.health
     }
 }
 
 fn handle_frame(
     sacn_source: &mut sacn::source::Source,
     universe_ids: &[UniverseId],
+    send_filter: &mut SendFilter,
     frame: Multiverse,
 ) -> anyhow::Result<()> {
+    let mut sent = false;
     for universe_id in universe_ids {
         let Some(universe) = frame.universe(universe_id) else {
             continue;
         };
 
+        if !send_filter.should_send(*universe_id, universe) {
+            continue;
+        }
+
         let data = universe.values().map(|v| v.as_u8());
 
         let mut sacn_universe = Universe::new(universe_id.as_u16());
         sacn_universe.data_slots = data.into();
+        sacn_source.send_universe_data_packet(sacn_universe)?;
+        sent = true;
+    }
+
+    if sent {
+        sacn_source.send_sync_packet()?;
+    }
+
+    let universe_numbers = universe_ids.iter().map(|id| id.as_u16()).collect::<Vec<_>>();
+    sacn_source.send_universe_discovery(&universe_numbers)?;
 
-        // FIXME: Implement unicast.
-        // FIXME: Implement synchronization.
+    Ok(())
+}
+
+/// Patched channels get the instance priority, all other channels 0 so they can be controlled
+/// by other sources.
+fn per_address_priorities(
+    universe_ids: &[UniverseId],
+    patched_addresses: &HashSet<Address>,
+    priority: u8,
+) -> HashMap<UniverseId, [u8; MAX_UNIVERSE_SIZE]> {
+    let mut priorities =
+        universe_ids.iter().map(|id| (*id, [0; MAX_UNIVERSE_SIZE])).collect::<HashMap<_, _>>();
+
+    for address in patched_addresses {
+        if let Some(universe) = priorities.get_mut(&address.universe()) {
+            universe[address.channel().as_index()] = priority;
+        }
+    }
+
+    priorities
+}
+
+fn send_priorities(
+    sacn_source: &sacn::source::Source,
+    priorities: &HashMap<UniverseId, [u8; MAX_UNIVERSE_SIZE]>,
+) -> anyhow::Result<()> {
+    for (universe_id, priorities) in priorities {
+        let mut sacn_universe =
+            Universe::with_start_code(universe_id.as_u16(), PER_ADDRESS_PRIORITY_START_CODE);
+        sacn_universe.data_slots = (*priorities).into();
         sacn_source.send_universe_data_packet(sacn_universe)?;
     }
 
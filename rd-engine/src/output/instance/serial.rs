@@ -0,0 +1,138 @@
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::Context as _;
+use thread_priority::ThreadBuilderExt;
+
+use crate::{
+    dmx::{Multiverse, UniverseId},
+    output::{
+        SerialBreakMode, SerialDmxOutputInstanceDefinition,
+        instance::timing,
+        protocol::serial::{BreakMode, DmxPort, FrameTiming},
+        status::InstanceHealth,
+    },
+};
+
+pub struct SerialInstance {
+    universe_id: UniverseId,
+    path: String,
+    break_mode: SerialBreakMode,
+    timing: FrameTiming,
+    refresh_rate: f32,
+
+    health: Arc<InstanceHealth>,
+
+    thread_handle: Option<JoinHandle<()>>,
+    thread_running: Arc<AtomicBool>,
+}
+
+impl SerialInstance {
+    pub fn new(definition: SerialDmxOutputInstanceDefinition) -> anyhow::Result<Self> {
+        Ok(Self {
+            universe_id: definition.universe_id,
+            path: definition.path,
+            break_mode: definition.break_mode,
+            timing: FrameTiming {
+                break_time: Duration::from_micros(definition.break_time_us.into()),
+                mark_after_break: Duration::from_micros(definition.mark_after_break_us.into()),
+            },
+            refresh_rate: definition.refresh_rate,
+
+            health: Arc::new(InstanceHealth::default()),
+
+            thread_handle: None,
+            thread_running: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn start(&mut self, multiverse: Arc<RwLock<Multiverse>>) -> anyhow::Result<()> {
+        if self.thread_handle.is_some() {
+            log::warn!("Serial instance '{}' thread already running", self.path);
+            return Ok(());
+        }
+
+        let interval = timing::refresh_interval(self.refresh_rate)?;
+        let break_mode = match self.break_mode {
+            SerialBreakMode::Ioctl => BreakMode::Ioctl,
+            SerialBreakMode::BaudRate => BreakMode::BaudRate,
+        };
+        let mut port = DmxPort::open(&self.path, break_mode, self.timing)
+            .with_context(|| format!("Failed to open serial port '{}'", self.path))?;
+
+        let universe_id = self.universe_id;
+        let path = self.path.clone();
+        let health = self.health.clone();
+        let running = self.thread_running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let handle = thread::Builder::new()
+            .name(format!("rd_serial_{}", self.path))
+            .spawn_with_priority(thread_priority::ThreadPriority::Max, move |prio_result| {
+                if prio_result.is_err() {
+                    log::warn!(
+                        "could not set {} thread priority to max",
+                        thread::current().name().unwrap_or("<unnamed>")
+                    );
+                }
+
+                timing::run_at_interval(&running, interval, || {
+                    let frame = multiverse.read().unwrap().clone();
+                    if let Some(universe) = frame.universe(&universe_id) {
+                        let data = universe.values().map(|v| v.as_u8());
+                        match port.send_frame(&data) {
+                            Ok(()) => health.frame_sent(),
+                            Err(err) => {
+                                log::error!("Serial instance '{path}' failed to send frame: {err}");
+                                health.error(err);
+                            }
+                        }
+                    }
+                });
+            })
+            .context("Failed to spawn serial instance thread")?;
+
+        self.thread_handle = Some(handle);
+        self.health.started();
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if self.thread_handle.is_some() {
+            self.thread_running.store(false, Ordering::SeqCst);
+            if let Some(handle) = self.thread_handle.take() {
+                let _ = handle.join();
+            }
+        }
+        self.health.stopped();
+    }
+
+    pub fn universe_id(&self) -> UniverseId {
+        self.universe_id
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn break_mode(&self) -> SerialBreakMode {
+        self.break_mode
+    }
+
+    pub fn timing(&self) -> FrameTiming {
+        self.timing
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
+
+    pub(crate) fn health(&self) -> &InstanceHealth {
+        &self.health
+    }
+}
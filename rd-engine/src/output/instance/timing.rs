@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::dmx::{Universe, UniverseId};
+
+/// The highest refresh rate an instance can be configured with.
+const MAX_REFRESH_RATE: f32 = 1000.0;
+
+/// After data changed, it is repeated this many times before falling back to
+/// keepalives, so receivers that missed the change still pick it up (E1.31 6.6.1).
+const CHANGE_REPEAT_COUNT: u8 = 3;
+
+/// The time between two frames at `refresh_rate` frames per second.
+pub(crate) fn refresh_interval(refresh_rate: f32) -> anyhow::Result<Duration> {
+    if !(refresh_rate > 0.0 && refresh_rate <= MAX_REFRESH_RATE) {
+        anyhow::bail!(
+            "Refresh rate must be between 0 and {MAX_REFRESH_RATE} Hz, got {refresh_rate}"
+        );
+    }
+    Ok(Duration::from_secs_f32(1.0 / refresh_rate))
+}
+
+/// Calls `f` every `interval` until `running` is cleared.
+pub(crate) fn run_at_interval(running: &AtomicBool, interval: Duration, mut f: impl FnMut()) {
+    let sleeper = spin_sleep::SpinSleeper::default();
+    let mut next_tick = Instant::now() + interval;
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+
+        if now < next_tick {
+            sleeper.sleep_until(next_tick);
+        } else {
+            let deviation = (now - next_tick).as_secs_f64();
+            if now > next_tick + interval {
+                // If we are more than one tick late, skip ahead to catch up.
+                let ticks_missed = (deviation / interval.as_secs_f64()).floor() as u32 + 1;
+                next_tick += interval * ticks_missed;
+            }
+        }
+
+        f();
+
+        next_tick += interval;
+    }
+}
+
+/// Decides which universes to send, so unchanged universes are only sent as keepalives.
+///
+/// Without a keepalive interval every universe is sent on every frame.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SendFilter {
+    keepalive_interval: Option<Duration>,
+    universes: HashMap<UniverseId, SentUniverse>,
+}
+
+#[derive(Debug, Clone)]
+struct SentUniverse {
+    universe: Universe,
+    repeats_left: u8,
+    last_sent: Instant,
+}
+
+impl SendFilter {
+    pub fn new(keepalive_interval: Option<Duration>) -> Self {
+        Self { keepalive_interval, universes: HashMap::new() }
+    }
+
+    /// Whether `universe` should be sent now. Assumes it is sent if this returns `true`.
+    pub fn should_send(&mut self, universe_id: UniverseId, universe: &Universe) -> bool {
+        self.should_send_at(universe_id, universe, Instant::now())
+    }
+
+    fn should_send_at(
+        &mut self,
+        universe_id: UniverseId,
+        universe: &Universe,
+        now: Instant,
+    ) -> bool {
+        let Some(keepalive_interval) = self.keepalive_interval else {
+            return true;
+        };
+
+        let Some(sent) = self.universes.get_mut(&universe_id) else {
+            self.universes.insert(
+                universe_id,
+                SentUniverse {
+                    universe: universe.clone(),
+                    repeats_left: CHANGE_REPEAT_COUNT,
+                    last_sent: now,
+                },
+            );
+            return true;
+        };
+
+        if sent.universe != *universe {
+            sent.universe = universe.clone();
+            sent.repeats_left = CHANGE_REPEAT_COUNT;
+        } else if sent.repeats_left > 0 {
+            sent.repeats_left -= 1;
+        } else if now - sent.last_sent < keepalive_interval {
+            return false;
+        }
+
+        sent.last_sent = now;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dmx::{Channel, Value};
+
+    #[test]
+    fn send_on_change_with_keepalive() {
+        let mut filter = SendFilter::new(Some(Duration::from_secs(1)));
+        let id = UniverseId::MIN;
+        let mut universe = Universe::new();
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        // The first frame and three repeats.
+        for millis in 0..4 {
+            assert!(filter.should_send_at(id, &universe, at(millis)));
+        }
+        assert!(!filter.should_send_at(id, &universe, at(500)));
+        assert!(filter.should_send_at(id, &universe, at(1003)));
+        assert!(!filter.should_send_at(id, &universe, at(1500)));
+
+        universe.set_value(&Channel::MIN, Value(255));
+        assert!(filter.should_send_at(id, &universe, at(1501)));
+
+        assert!(SendFilter::new(None).should_send(id, &universe));
+    }
+}
@@ -1,7 +1,12 @@
 mod agent;
 mod definition;
 mod instance;
-mod protocol;
+pub(crate) mod protocol;
+mod recording;
+mod routing;
+mod status;
 
 pub use agent::*;
 pub use definition::*;
+pub use recording::{Player, RecordedFrame, Recorder};
+pub use status::{OutputInstanceState, OutputInstanceStatus};
@@ -0,0 +1,119 @@
+//! An Art-Net Controller.
+//!
+//! Responsible for sending Art-Net packets and discovering nodes.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use super::{ArtDmx, ArtPoll, ArtPollReply, ArtSync, PORT, Packet, PortAddress};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// Nodes that haven't replied for this long are considered gone.
+const NODE_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_PACKET_SIZE: usize = 1024;
+
+/// Where a [Controller] sends its packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    Broadcast(Ipv4Addr),
+    Unicast(Vec<Ipv4Addr>),
+}
+
+/// An Art-Net Controller.
+///
+/// Responsible for sending Art-Net packets and discovering nodes.
+pub struct Controller {
+    socket: UdpSocket,
+    destination: Destination,
+    broadcast_address: Ipv4Addr,
+    sequence_numbers: HashMap<PortAddress, u8>,
+
+    last_poll_time: Option<Instant>,
+    nodes: HashMap<Ipv4Addr, (ArtPollReply, Instant)>,
+}
+
+impl Controller {
+    /// Creates a new [Controller], bound to the Art-Net port so it receives poll replies.
+    pub fn new(destination: Destination, broadcast_address: Ipv4Addr) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        // Other Art-Net software on the same machine also binds to the Art-Net port.
+        socket.set_reuse_address(true)?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, PORT)).into())?;
+
+        Ok(Self {
+            socket: socket.into(),
+            destination,
+            broadcast_address,
+            sequence_numbers: HashMap::new(),
+            last_poll_time: None,
+            nodes: HashMap::new(),
+        })
+    }
+
+    pub fn send_dmx(&mut self, port_address: PortAddress, data: Vec<u8>) -> io::Result<()> {
+        let sequence = self.next_sequence_number(port_address);
+        let bytes = ArtDmx::new(sequence, port_address, data).encode();
+        self.send(&bytes)
+    }
+
+    pub fn send_sync(&self) -> io::Result<()> {
+        self.send(&ArtSync.encode())
+    }
+
+    /// Polls for nodes when due, and processes any replies that came in since the last call.
+    pub fn poll(&mut self) -> io::Result<()> {
+        if self.last_poll_time.is_none_or(|time| time.elapsed() >= POLL_INTERVAL) {
+            let target = SocketAddrV4::new(self.broadcast_address, PORT);
+            self.socket.send_to(&ArtPoll::default().encode(), target)?;
+            self.last_poll_time = Some(Instant::now());
+        }
+
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            let len = match self.socket.recv(&mut buffer) {
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            };
+
+            if let Ok(Packet::PollReply(reply)) = Packet::decode(&buffer[..len]) {
+                self.nodes.insert(reply.ip, (reply, Instant::now()));
+            }
+        }
+
+        self.nodes.retain(|_, (_, last_seen)| last_seen.elapsed() < NODE_TIMEOUT);
+        Ok(())
+    }
+
+    /// The nodes that replied to recent polls.
+    pub fn nodes(&self) -> impl Iterator<Item = &ArtPollReply> {
+        self.nodes.values().map(|(reply, _)| reply)
+    }
+
+    fn send(&self, bytes: &[u8]) -> io::Result<()> {
+        match &self.destination {
+            Destination::Broadcast(address) => {
+                self.socket.send_to(bytes, SocketAddrV4::new(*address, PORT))?;
+            }
+            Destination::Unicast(addresses) => {
+                for address in addresses {
+                    self.socket.send_to(bytes, SocketAddrV4::new(*address, PORT))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn next_sequence_number(&mut self, port_address: PortAddress) -> u8 {
+        let sequence = self.sequence_numbers.entry(port_address).or_default();
+        // Zero disables resequencing on the node, so it is skipped.
+        *sequence = sequence.checked_add(1).unwrap_or(1);
+        *sequence
+    }
+}
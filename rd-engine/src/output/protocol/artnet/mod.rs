@@ -0,0 +1,24 @@
+//! Art-Net 4.
+//!
+//! Only the parts needed by a controller are implemented: sending DMX data
+//! and synchronization, and discovering nodes by polling.
+
+pub mod controller;
+mod packet;
+
+pub use packet::{ArtDmx, ArtPoll, ArtPollReply, ArtSync, Packet};
+
+/// The UDP port used for all Art-Net traffic.
+pub const PORT: u16 = 6454;
+
+/// The Art-Net protocol revision implemented.
+pub const PROTOCOL_VERSION: u16 = 14;
+
+/// A 15 bit Port-Address, made up of a 7 bit net, 4 bit sub-net and 4 bit universe.
+pub type PortAddress = u16;
+
+/// The highest valid [PortAddress].
+pub const MAX_PORT_ADDRESS: PortAddress = 0x7FFF;
+
+/// The maximum number of DMX slots in an ArtDmx packet.
+pub const MAX_UNIVERSE_SIZE: usize = 512;
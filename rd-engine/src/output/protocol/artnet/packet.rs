@@ -0,0 +1,269 @@
+//! # Packets
+//!
+//! Every Art-Net packet starts with the `Art-Net` ID and a little endian op code.
+
+use std::net::Ipv4Addr;
+
+use super::{MAX_PORT_ADDRESS, MAX_UNIVERSE_SIZE, PROTOCOL_VERSION, PortAddress};
+
+const ID: &[u8; 8] = b"Art-Net\0";
+
+const OP_POLL: u16 = 0x2000;
+const OP_POLL_REPLY: u16 = 0x2100;
+const OP_DMX: u16 = 0x5000;
+const OP_SYNC: u16 = 0x5200;
+
+/// ArtPollReply packets from older nodes can be shorter than the current specification.
+const MIN_POLL_REPLY_LEN: usize = 207;
+
+/// Error type returned when decoding a [Packet].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PacketError {
+    #[error("not an Art-Net packet")]
+    InvalidId,
+    #[error("packet is truncated")]
+    Truncated,
+    #[error("unsupported op code: {0:#06x}")]
+    UnsupportedOpCode(u16),
+    #[error("invalid port address: {0}")]
+    InvalidPortAddress(u16),
+}
+
+/// An Art-Net packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    Poll(ArtPoll),
+    PollReply(ArtPollReply),
+    Dmx(ArtDmx),
+    Sync(ArtSync),
+}
+
+impl Packet {
+    /// Decodes a network ordered slice of bytes into a new [Packet].
+    pub fn decode(data: &[u8]) -> Result<Self, PacketError> {
+        let header = data.get(..10).ok_or(PacketError::Truncated)?;
+        if &header[..8] != ID {
+            return Err(PacketError::InvalidId);
+        }
+
+        match u16::from_le_bytes([header[8], header[9]]) {
+            OP_POLL => Ok(Packet::Poll(ArtPoll::decode(data)?)),
+            OP_POLL_REPLY => Ok(Packet::PollReply(ArtPollReply::decode(data)?)),
+            OP_DMX => Ok(Packet::Dmx(ArtDmx::decode(data)?)),
+            OP_SYNC => Ok(Packet::Sync(ArtSync)),
+            op_code => Err(PacketError::UnsupportedOpCode(op_code)),
+        }
+    }
+}
+
+/// Discovers nodes on the network, which answer with an [ArtPollReply].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArtPoll {
+    pub flags: u8,
+    pub diag_priority: u8,
+}
+
+impl ArtPoll {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = header(OP_POLL);
+        bytes.extend(PROTOCOL_VERSION.to_be_bytes());
+        bytes.extend([self.flags, self.diag_priority]);
+        bytes
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, PacketError> {
+        let flags = *data.get(12).ok_or(PacketError::Truncated)?;
+        let diag_priority = data.get(13).copied().unwrap_or_default();
+        Ok(Self { flags, diag_priority })
+    }
+}
+
+/// Describes a node and the Port-Addresses it outputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtPollReply {
+    pub ip: Ipv4Addr,
+    pub short_name: String,
+    pub long_name: String,
+    /// The Port-Addresses of the node's DMX output ports.
+    pub output_port_addresses: Vec<PortAddress>,
+}
+
+impl ArtPollReply {
+    const PORT_TYPE_OUTPUT: u8 = 0x80;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = header(OP_POLL_REPLY);
+        bytes.extend(self.ip.octets());
+        bytes.extend(super::PORT.to_le_bytes());
+        bytes.extend([0; 2]); // VersInfo
+
+        // All output ports have to share the net and sub-net.
+        let first = self.output_port_addresses.first().copied().unwrap_or_default();
+        bytes.extend([(first >> 8) as u8 & 0x7F, (first >> 4) as u8 & 0x0F]);
+        bytes.extend([0; 6]); // Oem, Ubea, Status1, EstaMan
+
+        write_name(&mut bytes, &self.short_name, 18);
+        write_name(&mut bytes, &self.long_name, 64);
+        bytes.extend([0; 64]); // NodeReport
+
+        let ports = &self.output_port_addresses[..self.output_port_addresses.len().min(4)];
+        bytes.extend((ports.len() as u16).to_be_bytes());
+
+        let mut port_types = [0; 4];
+        let mut sw_out = [0; 4];
+        for (ix, port_address) in ports.iter().enumerate() {
+            port_types[ix] = Self::PORT_TYPE_OUTPUT;
+            sw_out[ix] = *port_address as u8 & 0x0F;
+        }
+        bytes.extend(port_types);
+        bytes.extend([0; 4]); // GoodInput
+        bytes.extend([0; 4]); // GoodOutputA
+        bytes.extend([0; 4]); // SwIn
+        bytes.extend(sw_out);
+        bytes.resize(MIN_POLL_REPLY_LEN, 0);
+        bytes
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, PacketError> {
+        if data.len() < MIN_POLL_REPLY_LEN {
+            return Err(PacketError::Truncated);
+        }
+
+        let ip = Ipv4Addr::new(data[10], data[11], data[12], data[13]);
+        let net = u16::from(data[18] & 0x7F);
+        let sub_net = u16::from(data[19] & 0x0F);
+        let short_name = read_name(&data[26..44]);
+        let long_name = read_name(&data[44..108]);
+        let num_ports = usize::from(u16::from_be_bytes([data[172], data[173]]).min(4));
+        let port_types = &data[174..178];
+        let sw_out = &data[190..194];
+
+        let output_port_addresses = (0..num_ports)
+            .filter(|&ix| port_types[ix] & Self::PORT_TYPE_OUTPUT != 0)
+            .map(|ix| net << 8 | sub_net << 4 | u16::from(sw_out[ix] & 0x0F))
+            .collect();
+
+        Ok(Self { ip, short_name, long_name, output_port_addresses })
+    }
+}
+
+/// DMX data for a single Port-Address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtDmx {
+    /// Increments for every packet on a Port-Address, skipping 0 which disables resequencing.
+    pub sequence: u8,
+    pub physical: u8,
+    pub port_address: PortAddress,
+    pub data: Vec<u8>,
+}
+
+impl ArtDmx {
+    pub fn new(sequence: u8, port_address: PortAddress, data: Vec<u8>) -> Self {
+        Self { sequence, physical: 0, port_address, data }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = self.data[..self.data.len().min(MAX_UNIVERSE_SIZE)].to_vec();
+        // The length has to be even and at least 2.
+        data.resize(data.len().next_multiple_of(2).max(2), 0);
+
+        let mut bytes = header(OP_DMX);
+        bytes.extend(PROTOCOL_VERSION.to_be_bytes());
+        bytes.extend([self.sequence, self.physical]);
+        bytes.extend(self.port_address.to_le_bytes());
+        bytes.extend((data.len() as u16).to_be_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, PacketError> {
+        let header = data.get(..18).ok_or(PacketError::Truncated)?;
+        let port_address = u16::from_le_bytes([header[14], header[15]]);
+        if port_address > MAX_PORT_ADDRESS {
+            return Err(PacketError::InvalidPortAddress(port_address));
+        }
+
+        let len = usize::from(u16::from_be_bytes([header[16], header[17]]));
+        let dmx = data.get(18..18 + len).ok_or(PacketError::Truncated)?;
+
+        Ok(Self { sequence: header[12], physical: header[13], port_address, data: dmx.to_vec() })
+    }
+}
+
+/// Tells nodes to output the DMX data they received since the last ArtSync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArtSync;
+
+impl ArtSync {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = header(OP_SYNC);
+        bytes.extend(PROTOCOL_VERSION.to_be_bytes());
+        bytes.extend([0, 0]); // Aux1, Aux2
+        bytes
+    }
+}
+
+fn header(op_code: u16) -> Vec<u8> {
+    let mut bytes = ID.to_vec();
+    bytes.extend(op_code.to_le_bytes());
+    bytes
+}
+
+fn write_name(bytes: &mut Vec<u8>, name: &str, len: usize) {
+    // Names are null terminated, so leave room for at least one null byte.
+    let name = &name.as_bytes()[..name.len().min(len - 1)];
+    bytes.extend(name);
+    bytes.extend(std::iter::repeat_n(0, len - name.len()));
+}
+
+fn read_name(data: &[u8]) -> String {
+    let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_dmx() {
+        let bytes = ArtDmx::new(7, 0x0123, vec![1, 2, 3]).encode();
+        assert_eq!(&bytes[..8], b"Art-Net\0");
+        assert_eq!(&bytes[8..18], &[0x00, 0x50, 0, 14, 7, 0, 0x23, 0x01, 0, 4]);
+        assert_eq!(&bytes[18..], &[1, 2, 3, 0]);
+
+        let Ok(Packet::Dmx(dmx)) = Packet::decode(&bytes) else { panic!() };
+        assert_eq!(dmx.port_address, 0x0123);
+        assert_eq!(dmx.data, vec![1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn encode_sync_and_poll() {
+        assert_eq!(ArtSync.encode(), b"Art-Net\0\x00\x52\x00\x0e\x00\x00".to_vec());
+        assert_eq!(ArtPoll::default().encode(), b"Art-Net\0\x00\x20\x00\x0e\x00\x00".to_vec());
+    }
+
+    #[test]
+    fn poll_reply_round_trip() {
+        let reply = ArtPollReply {
+            ip: Ipv4Addr::new(2, 0, 0, 10),
+            short_name: "Node".to_string(),
+            long_name: "Four port node".to_string(),
+            output_port_addresses: vec![0x0120, 0x0121],
+        };
+
+        let bytes = reply.encode();
+        assert_eq!(bytes.len(), MIN_POLL_REPLY_LEN);
+        assert_eq!(Packet::decode(&bytes), Ok(Packet::PollReply(reply)));
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(Packet::decode(b"Art-Net\0"), Err(PacketError::Truncated));
+        assert_eq!(Packet::decode(b"Art-Nyt\0\x00\x50"), Err(PacketError::InvalidId));
+        assert_eq!(
+            Packet::decode(b"Art-Net\0\x00\x99"),
+            Err(PacketError::UnsupportedOpCode(0x9900))
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! # Messages
+//!
+//! Every message is framed as a start byte, a label, a little endian data length,
+//! the data and an end byte.
+
+use std::fmt;
+
+use crate::dmx::{MAX_UNIVERSE_SIZE, NULL_START_CODE};
+
+const START_OF_MESSAGE: u8 = 0x7E;
+const END_OF_MESSAGE: u8 = 0xE7;
+const HEADER_LEN: usize = 4;
+/// The largest data length the widget API allows in a single message.
+const MAX_DATA_LEN: usize = 600;
+
+/// The labels identifying the kind of a [Message].
+pub mod label {
+    pub const GET_WIDGET_PARAMETERS: u8 = 3;
+    pub const RECEIVED_DMX: u8 = 5;
+    pub const OUTPUT_ONLY_SEND_DMX: u8 = 6;
+    pub const RECEIVE_DMX_ON_CHANGE: u8 = 8;
+    pub const GET_WIDGET_SERIAL_NUMBER: u8 = 10;
+}
+
+/// Error type returned when decoding the data of a [Message].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MessageError {
+    #[error("expected message with label {expected}, got {actual}")]
+    UnexpectedLabel { expected: u8, actual: u8 },
+    #[error("message data is truncated")]
+    Truncated,
+}
+
+/// A widget API message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub label: u8,
+    pub data: Vec<u8>,
+}
+
+impl Message {
+    pub fn new(label: u8, data: Vec<u8>) -> Self {
+        Self { label, data }
+    }
+
+    /// Outputs a DMX frame, sent with the null start code.
+    pub fn output_only_send_dmx(data: &[u8]) -> Self {
+        let data = &data[..data.len().min(MAX_UNIVERSE_SIZE)];
+        let mut bytes = Vec::with_capacity(1 + data.len());
+        bytes.push(NULL_START_CODE);
+        bytes.extend(data);
+        Self::new(label::OUTPUT_ONLY_SEND_DMX, bytes)
+    }
+
+    /// Requests the [WidgetParameters], without any user configuration data.
+    pub fn get_widget_parameters() -> Self {
+        Self::new(label::GET_WIDGET_PARAMETERS, vec![0, 0])
+    }
+
+    /// Requests the [SerialNumber].
+    pub fn get_widget_serial_number() -> Self {
+        Self::new(label::GET_WIDGET_SERIAL_NUMBER, Vec::new())
+    }
+
+    /// Sets whether received DMX is reported on every frame, or only when it changed.
+    pub fn receive_dmx_on_change(on_change: bool) -> Self {
+        Self::new(label::RECEIVE_DMX_ON_CHANGE, vec![on_change as u8])
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.data.len() + 1);
+        bytes.push(START_OF_MESSAGE);
+        bytes.push(self.label);
+        bytes.extend((self.data.len() as u16).to_le_bytes());
+        bytes.extend(&self.data);
+        bytes.push(END_OF_MESSAGE);
+        bytes
+    }
+
+    fn expect_label(&self, expected: u8) -> Result<(), MessageError> {
+        if self.label != expected {
+            return Err(MessageError::UnexpectedLabel { expected, actual: self.label });
+        }
+        Ok(())
+    }
+}
+
+/// The reply to [Message::get_widget_parameters].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidgetParameters {
+    pub firmware_version: u16,
+    /// The DMX break time, in units of 10.67 microseconds.
+    pub break_time: u8,
+    /// The DMX mark after break time, in units of 10.67 microseconds.
+    pub mab_time: u8,
+    /// The DMX output rate in packets per second, where 0 means as fast as possible.
+    pub refresh_rate: u8,
+}
+
+impl WidgetParameters {
+    pub fn decode(message: &Message) -> Result<Self, MessageError> {
+        message.expect_label(label::GET_WIDGET_PARAMETERS)?;
+        let data = message.data.get(..5).ok_or(MessageError::Truncated)?;
+        Ok(Self {
+            firmware_version: u16::from_le_bytes([data[0], data[1]]),
+            break_time: data[2],
+            mab_time: data[3],
+            refresh_rate: data[4],
+        })
+    }
+}
+
+/// The reply to [Message::get_widget_serial_number].
+///
+/// The serial number is BCD encoded, so its hexadecimal representation reads as the
+/// decimal serial number printed on the widget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialNumber(pub u32);
+
+impl SerialNumber {
+    pub fn decode(message: &Message) -> Result<Self, MessageError> {
+        message.expect_label(label::GET_WIDGET_SERIAL_NUMBER)?;
+        let data = message.data.get(..4).ok_or(MessageError::Truncated)?;
+        Ok(Self(u32::from_le_bytes([data[0], data[1], data[2], data[3]])))
+    }
+}
+
+impl fmt::Display for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08X}", self.0)
+    }
+}
+
+/// A DMX frame received by the widget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedDmx {
+    status: u8,
+    pub start_code: u8,
+    pub data: Vec<u8>,
+}
+
+impl ReceivedDmx {
+    const QUEUE_OVERFLOW_BIT: u8 = 0b01;
+    const OVERRUN_BIT: u8 = 0b10;
+
+    pub fn decode(message: &Message) -> Result<Self, MessageError> {
+        message.expect_label(label::RECEIVED_DMX)?;
+        let [status, start_code, data @ ..] = message.data.as_slice() else {
+            return Err(MessageError::Truncated);
+        };
+        Ok(Self { status: *status, start_code: *start_code, data: data.to_vec() })
+    }
+
+    /// Whether the widget dropped frames because the host didn't read them fast enough.
+    pub fn queue_overflow(&self) -> bool {
+        self.status & Self::QUEUE_OVERFLOW_BIT != 0
+    }
+
+    /// Whether the widget missed data within this frame.
+    pub fn overrun(&self) -> bool {
+        self.status & Self::OVERRUN_BIT != 0
+    }
+}
+
+/// Splits a stream of bytes from the widget into [Message]s.
+///
+/// Bytes that don't form a valid message are skipped, so the parser resynchronizes
+/// on the next start byte.
+#[derive(Debug, Clone, Default)]
+pub struct Parser {
+    buffer: Vec<u8>,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds received bytes and returns all messages that are complete.
+    pub fn parse(&mut self, bytes: &[u8]) -> Vec<Message> {
+        self.buffer.extend(bytes);
+
+        let mut messages = Vec::new();
+        loop {
+            match self.buffer.iter().position(|&b| b == START_OF_MESSAGE) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    self.buffer.clear();
+                    break;
+                }
+            }
+
+            let Some(header) = self.buffer.get(..HEADER_LEN) else {
+                break;
+            };
+
+            let len = usize::from(u16::from_le_bytes([header[2], header[3]]));
+            if len > MAX_DATA_LEN {
+                self.buffer.remove(0);
+                continue;
+            }
+
+            let Some(&end) = self.buffer.get(HEADER_LEN + len) else {
+                break;
+            };
+
+            if end != END_OF_MESSAGE {
+                self.buffer.remove(0);
+                continue;
+            }
+
+            let label = self.buffer[1];
+            let data = self.buffer[HEADER_LEN..HEADER_LEN + len].to_vec();
+            self.buffer.drain(..=HEADER_LEN + len);
+            messages.push(Message::new(label, data));
+        }
+
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_send_dmx() {
+        let bytes = Message::output_only_send_dmx(&[1, 2, 3]).encode();
+        assert_eq!(bytes, vec![0x7E, 6, 4, 0, 0, 1, 2, 3, 0xE7]);
+    }
+
+    #[test]
+    fn parse_split_and_garbage() {
+        let mut stream = vec![0x12, 0xE7, 0x7E];
+        stream.extend(Message::new(label::RECEIVED_DMX, vec![0, 0, 10, 20]).encode());
+        stream.extend(
+            Message::new(label::GET_WIDGET_SERIAL_NUMBER, vec![0x78, 0x56, 0x34, 0x12]).encode(),
+        );
+
+        let mut parser = Parser::new();
+        let mut messages = parser.parse(&stream[..7]);
+        assert!(messages.is_empty());
+        messages.extend(parser.parse(&stream[7..]));
+        assert_eq!(messages.len(), 2);
+
+        let dmx = ReceivedDmx::decode(&messages[0]).unwrap();
+        assert_eq!((dmx.start_code, dmx.data.as_slice()), (0, [10, 20].as_slice()));
+        assert!(!dmx.overrun() && !dmx.queue_overflow());
+
+        let serial_number = SerialNumber::decode(&messages[1]).unwrap();
+        assert_eq!(serial_number.to_string(), "12345678");
+    }
+
+    #[test]
+    fn decode_widget_parameters() {
+        let message = Message::new(label::GET_WIDGET_PARAMETERS, vec![0x44, 0x01, 9, 1, 40]);
+        let parameters = WidgetParameters::decode(&message).unwrap();
+        assert_eq!(parameters.firmware_version, 0x0144);
+        assert_eq!(parameters.refresh_rate, 40);
+
+        assert_eq!(
+            ReceivedDmx::decode(&message),
+            Err(MessageError::UnexpectedLabel { expected: 5, actual: 3 })
+        );
+    }
+}
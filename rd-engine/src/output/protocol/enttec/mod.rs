@@ -0,0 +1,12 @@
+//! Enttec DMX USB Pro widget API.
+//!
+//! The widget exchanges label-framed messages with the host over a USB serial link,
+//! either through the FTDI D2XX driver or a plain serial port.
+
+mod message;
+pub mod widget;
+
+pub use message::{Message, Parser, ReceivedDmx, SerialNumber, WidgetParameters, label};
+
+/// The baud rate of the virtual serial port. The widget ignores it, but serial drivers need one.
+pub const BAUD_RATE: u32 = 57600;
@@ -0,0 +1,93 @@
+//! A connection to a DMX USB Pro widget.
+
+use std::io::{self, Read, Write};
+
+use super::{Message, Parser};
+
+const READ_BUFFER_SIZE: usize = 1024;
+
+/// A byte stream to a widget, like a serial port.
+pub trait Port: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Port for T {}
+
+/// A connection to a DMX USB Pro widget.
+pub struct Widget<P> {
+    port: P,
+    parser: Parser,
+}
+
+impl<P: Read + Write> Widget<P> {
+    pub fn new(port: P) -> Self {
+        Self { port, parser: Parser::new() }
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.port.write_all(&message.encode())?;
+        self.port.flush()
+    }
+
+    pub fn send_dmx(&mut self, data: &[u8]) -> io::Result<()> {
+        self.send(&Message::output_only_send_dmx(data))
+    }
+
+    /// Reads the bytes that are available and returns the messages they completed.
+    ///
+    /// Blocks for at most the read timeout of the port.
+    pub fn receive(&mut self) -> io::Result<Vec<Message>> {
+        let mut buffer = [0; READ_BUFFER_SIZE];
+        let len = match self.port.read(&mut buffer) {
+            Ok(len) => len,
+            Err(err)
+                if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) =>
+            {
+                0
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(self.parser.parse(&buffer[..len]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::output::protocol::enttec::label;
+
+    /// Reads canned widget replies and records everything written to it.
+    struct MockPort {
+        rx: Cursor<Vec<u8>>,
+        tx: Vec<u8>,
+    }
+
+    impl Read for MockPort {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.rx.read(buf)
+        }
+    }
+
+    impl Write for MockPort {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.tx.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn send_and_receive() {
+        let reply = Message::new(label::RECEIVED_DMX, vec![0, 0, 255]).encode();
+        let mut widget = Widget::new(MockPort { rx: Cursor::new(reply), tx: Vec::new() });
+
+        widget.send_dmx(&[7]).unwrap();
+        assert_eq!(widget.port.tx, Message::output_only_send_dmx(&[7]).encode());
+
+        let messages = widget.receive().unwrap();
+        assert_eq!(messages, vec![Message::new(label::RECEIVED_DMX, vec![0, 0, 255])]);
+        assert!(widget.receive().unwrap().is_empty());
+    }
+}
@@ -1 +1,4 @@
+pub mod artnet;
+pub mod enttec;
 pub mod sacn;
+pub mod serial;
@@ -2,6 +2,7 @@
 
 mod acn;
 mod packet;
+pub mod receiver;
 pub mod source;
 
 /// # E1.31 3.2 Universe
@@ -73,7 +74,11 @@ pub type ComponentIdentifier = uuid::Uuid;
 pub const DEFAULT_PORT: u16 = 5568;
 
 /// The universe number on which discovery packets will be sent.
-pub const DISCOVERY_UNIVERSE: u32 = 64214;
+pub const DISCOVERY_UNIVERSE: UniverseNumber = 64214;
 
 /// The maximum size of a universe.
 pub const MAX_UNIVERSE_SIZE: usize = 512;
+
+/// The start code of per-address priority data, where each slot holds the priority
+/// of the matching DMX slot. A priority of 0 means the slot is not controlled.
+pub const PER_ADDRESS_PRIORITY_START_CODE: Slot = 0xDD;
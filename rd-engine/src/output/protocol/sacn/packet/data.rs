@@ -87,9 +87,12 @@ impl DataFraming {
         )
     }
 
-    /// Returns the source name in this layer.
+    /// Returns the source name in this layer, without its null padding.
+    ///
+    /// Returns an empty name if a received name is not valid UTF-8.
     pub fn source_name(&self) -> &str {
-        core::str::from_utf8(&self.source_name).unwrap()
+        let len = self.source_name.iter().position(|&b| b == 0).unwrap_or(self.source_name.len());
+        core::str::from_utf8(&self.source_name[..len]).unwrap_or_default()
     }
 
     /// Returns the priority in this layer.
@@ -97,6 +100,11 @@ impl DataFraming {
         self.priority
     }
 
+    /// Returns the sequence number in this layer.
+    pub fn sequence_number(&self) -> u8 {
+        self.sequence_number
+    }
+
     /// Returns the synchronization address in this layer.
     pub fn synchronization_address(&self) -> u16 {
         self.synchronization_address
@@ -246,6 +254,10 @@ impl acn::Pdu for Dmp {
     }
 
     fn decode(bytes: &[u8]) -> Result<Self, Self::DecodeError> {
+        if bytes.len() < 10 {
+            return Err(PacketError::InvalidLength(bytes.len()));
+        }
+
         // E1.13 7.2 DMP Layer: Vector
         let vector = bytes[2];
         if vector != Self::VECTOR {
@@ -278,8 +290,10 @@ impl acn::Pdu for Dmp {
         let property_value_count = u16::from_be_bytes([bytes[8], bytes[9]]);
 
         // E1.13 7.7 Property Values (DMX512-A Data)
-        let mut property_values = ArrayVec::new();
-        property_values.extend(bytes[10..10 + property_value_count as usize].to_vec());
+        let property_values = bytes
+            .get(10..10 + property_value_count as usize)
+            .and_then(|values| ArrayVec::try_from(values).ok())
+            .ok_or(PacketError::InvalidLength(bytes.len()))?;
 
         Ok(Dmp { property_values })
     }
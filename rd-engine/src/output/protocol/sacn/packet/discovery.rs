@@ -21,9 +21,17 @@ impl DiscoveryFraming {
         Ok(Self { source_name, universe_discovery })
     }
 
-    /// The source name in this packet.
+    /// The source name in this packet, without its null padding.
+    ///
+    /// Returns an empty name if a received name is not valid UTF-8.
     pub fn source_name(&self) -> &str {
-        core::str::from_utf8(&self.source_name).unwrap()
+        let len = self.source_name.iter().position(|&b| b == 0).unwrap_or(self.source_name.len());
+        core::str::from_utf8(&self.source_name[..len]).unwrap_or_default()
+    }
+
+    /// The [UniverseDiscovery] layer in this packet.
+    pub fn universe_discovery(&self) -> &UniverseDiscovery {
+        &self.universe_discovery
     }
 
     pub(crate) fn from_source_config(
@@ -50,6 +58,10 @@ impl acn::Pdu for DiscoveryFraming {
     }
 
     fn decode(bytes: &[u8]) -> Result<Self, Self::DecodeError> {
+        if bytes.len() < 74 {
+            return Err(PacketError::InvalidLength(bytes.len()));
+        }
+
         // E1.31 6.4.1 Universe Discovery Packet: Vector
         let vector = [bytes[2], bytes[3], bytes[4], bytes[5]];
         if vector != Self::VECTOR {
@@ -122,6 +134,10 @@ impl acn::Pdu for UniverseDiscovery {
     }
 
     fn decode(bytes: &[u8]) -> Result<Self, Self::DecodeError> {
+        if bytes.len() < 8 {
+            return Err(PacketError::InvalidLength(bytes.len()));
+        }
+
         // E1.31 8.2 Universe Discovery Layer: Vector.
         let vector = [bytes[2], bytes[3], bytes[4], bytes[5]];
         if vector != Self::VECTOR {
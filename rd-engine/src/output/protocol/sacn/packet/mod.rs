@@ -15,7 +15,7 @@ mod root;
 mod sync;
 
 pub use data::{DataFraming, Dmp};
-pub use discovery::DiscoveryFraming;
+pub use discovery::{DiscoveryFraming, UniverseDiscovery};
 pub use error::PacketError;
 pub use root::RootLayer;
 pub use sync::SyncFraming;
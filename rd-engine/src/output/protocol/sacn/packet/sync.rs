@@ -30,6 +30,10 @@ impl acn::Pdu for SyncFraming {
     type DecodeError = PacketError;
 
     fn decode(bytes: &[u8]) -> Result<Self, Self::DecodeError> {
+        if bytes.len() < 11 {
+            return Err(PacketError::InvalidLength(bytes.len()));
+        }
+
         // E1.31 6.3.1 Synchronization Packet: Vector
         let vector = [bytes[2], bytes[3], bytes[4], bytes[5]];
         if vector != Self::VECTOR {
@@ -0,0 +1,440 @@
+//! An sACN Receiver.
+//!
+//! Responsible for receiving sACN packets, tracking the sources that send
+//! them and merging their data per universe.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use super::packet::{DiscoveryFraming, Packet, PacketError, Pdu};
+use super::source::multicast_address;
+use super::{
+    ComponentIdentifier, DEFAULT_PORT, DISCOVERY_UNIVERSE, MAX_UNIVERSE_SIZE, Slot, UniverseNumber,
+};
+use crate::dmx::NULL_START_CODE;
+
+/// E1.31 6.7.1: A source that hasn't sent data for this long is considered lost.
+const NETWORK_DATA_LOSS_TIMEOUT: Duration = Duration::from_millis(2500);
+/// Sources announce their universes every 10 seconds, so a few missed announcements mean it's gone.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_PACKET_SIZE: usize = 1144;
+
+/// Error type returned by a [Receiver].
+#[derive(Debug, thiserror::Error)]
+pub enum ReceiverError {
+    /// An [std::io::Error] wrapper.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// An [PacketError] wrapper.
+    #[error(transparent)]
+    Packet(#[from] PacketError),
+}
+
+/// How the data of multiple sources for the same universe is combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeMode {
+    /// Highest takes precedence across all sources, regardless of priority.
+    Htp,
+    /// Only the sources with the highest priority are used, merged HTP among themselves.
+    #[default]
+    Priority,
+}
+
+/// A source the [Receiver] is receiving data from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedSource {
+    pub name: String,
+    pub priority: u8,
+    pub data: [Slot; MAX_UNIVERSE_SIZE],
+    pub last_seen: Instant,
+    sequence_number: u8,
+}
+
+/// A source announcing its universes on the discovery universe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredSource {
+    pub name: String,
+    pub last_seen: Instant,
+    /// The universe lists of each discovery page, keyed by page number.
+    pages: BTreeMap<u8, Vec<UniverseNumber>>,
+}
+
+impl DiscoveredSource {
+    /// All universes the source announced, in ascending order.
+    pub fn universes(&self) -> impl Iterator<Item = &UniverseNumber> {
+        self.pages.values().flatten()
+    }
+}
+
+/// An sACN Receiver.
+///
+/// Responsible for receiving sACN packets, tracking the sources that send
+/// them and merging their data per universe.
+pub struct Receiver {
+    config: ReceiverConfig,
+
+    socket: UdpSocket,
+    universes: HashSet<UniverseNumber>,
+    sources: HashMap<UniverseNumber, HashMap<ComponentIdentifier, ReceivedSource>>,
+    discovered_sources: HashMap<ComponentIdentifier, DiscoveredSource>,
+    ignored_sources: HashSet<ComponentIdentifier>,
+}
+
+impl Receiver {
+    /// Creates a new [Receiver].
+    pub fn new(config: ReceiverConfig) -> Result<Self, ReceiverError> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        // Other sACN software on the same machine also binds to the sACN port.
+        socket.set_reuse_address(true)?;
+        // Binding to an interface address would filter out multicast traffic on some platforms,
+        // so the interface is only used to join the multicast groups.
+        socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, config.port)).into())?;
+
+        Ok(Self {
+            config,
+            socket: socket.into(),
+            universes: HashSet::new(),
+            sources: HashMap::new(),
+            discovered_sources: HashMap::new(),
+            ignored_sources: HashSet::new(),
+        })
+    }
+
+    /// Returns the [ReceiverConfig] for this [Receiver].
+    pub fn config(&self) -> &ReceiverConfig {
+        &self.config
+    }
+
+    /// Returns the port of the socket used by the [Receiver].
+    pub fn socket_port(&self) -> Option<u16> {
+        Some(self.socket.local_addr().ok()?.port())
+    }
+
+    /// Starts receiving data for the universe, and joins its multicast group.
+    ///
+    /// Unicast data for the universe is received even if joining the group fails.
+    pub fn join_universe(&mut self, universe: UniverseNumber) -> Result<(), ReceiverError> {
+        if !self.universes.insert(universe) {
+            return Ok(());
+        }
+
+        let interface = self.config.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        self.socket.join_multicast_v4(&multicast_address(universe), &interface)?;
+        Ok(())
+    }
+
+    /// Stops receiving data for the universe, and leaves its multicast group.
+    pub fn leave_universe(&mut self, universe: UniverseNumber) -> Result<(), ReceiverError> {
+        if !self.universes.remove(&universe) {
+            return Ok(());
+        }
+
+        self.sources.remove(&universe);
+        let interface = self.config.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        self.socket.leave_multicast_v4(&multicast_address(universe), &interface)?;
+        Ok(())
+    }
+
+    /// Starts listening for universe discovery packets, which are tracked in
+    /// [Receiver::discovered_sources].
+    pub fn join_discovery(&mut self) -> Result<(), ReceiverError> {
+        self.join_universe(DISCOVERY_UNIVERSE)
+    }
+
+    /// Drops the data of these sources, e.g. to not receive what this machine sends
+    /// itself when multicast loopback is enabled.
+    pub fn set_ignored_sources(&mut self, cids: HashSet<ComponentIdentifier>) {
+        for sources in self.sources.values_mut() {
+            sources.retain(|cid, _| !cids.contains(cid));
+        }
+        self.ignored_sources = cids;
+    }
+
+    /// Waits up to `timeout` for a packet and processes it.
+    ///
+    /// Returns the universe whose sources changed, if any. Changes to the discovered
+    /// sources are reported as [DISCOVERY_UNIVERSE]. Sources that timed out are
+    /// removed on every call, so this should be called regularly.
+    pub fn recv(&mut self, timeout: Duration) -> Result<Option<UniverseNumber>, ReceiverError> {
+        let mut changed = self.remove_lost_sources();
+
+        self.socket.set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        let len = match self.socket.recv(&mut buffer) {
+            Ok(len) => len,
+            Err(err)
+                if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) =>
+            {
+                return Ok(changed);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let packet = Packet::decode(&buffer[..len])?;
+        for root_layer in packet.block.pdus() {
+            if let Some(universe) = self.handle_pdu(*root_layer.cid(), root_layer.pdu()) {
+                changed = Some(universe);
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// The universes this [Receiver] has joined.
+    pub fn universes(&self) -> impl Iterator<Item = &UniverseNumber> {
+        self.universes.iter()
+    }
+
+    /// The sources currently sending data for the universe.
+    pub fn sources(
+        &self,
+        universe: UniverseNumber,
+    ) -> impl Iterator<Item = (&ComponentIdentifier, &ReceivedSource)> {
+        self.sources.get(&universe).into_iter().flatten()
+    }
+
+    /// The sources that announced their universes on the discovery universe.
+    pub fn discovered_sources(
+        &self,
+    ) -> impl Iterator<Item = (&ComponentIdentifier, &DiscoveredSource)> {
+        self.discovered_sources.iter()
+    }
+
+    /// Merges the data of all sources for the universe.
+    ///
+    /// Returns `None` if no source is sending data for the universe.
+    pub fn merge(
+        &self,
+        universe: UniverseNumber,
+        mode: MergeMode,
+    ) -> Option<[Slot; MAX_UNIVERSE_SIZE]> {
+        let sources = self.sources.get(&universe).filter(|sources| !sources.is_empty())?;
+
+        let max_priority = match mode {
+            MergeMode::Htp => 0,
+            MergeMode::Priority => sources.values().map(|s| s.priority).max().unwrap_or_default(),
+        };
+
+        let mut merged = [0; MAX_UNIVERSE_SIZE];
+        for source in sources.values().filter(|s| s.priority >= max_priority) {
+            for (merged, value) in merged.iter_mut().zip(source.data) {
+                *merged = (*merged).max(value);
+            }
+        }
+
+        Some(merged)
+    }
+
+    fn handle_pdu(&mut self, cid: ComponentIdentifier, pdu: &Pdu) -> Option<UniverseNumber> {
+        let data_framing = match pdu {
+            Pdu::DataFraming(data_framing) => data_framing,
+            Pdu::DiscoveryFraming(discovery_framing) => {
+                return self.handle_discovery(cid, discovery_framing);
+            }
+            Pdu::SyncFraming(_) => return None,
+        };
+
+        let universe = data_framing.universe();
+        if !self.universes.contains(&universe)
+            || data_framing.preview_data()
+            || self.ignored_sources.contains(&cid)
+        {
+            return None;
+        }
+
+        let sources = self.sources.entry(universe).or_default();
+
+        if data_framing.stream_terminated() {
+            return sources.remove(&cid).map(|_| universe);
+        }
+
+        if let Some(source) = sources.get(&cid) {
+            // E1.31 6.7.2: Discard out of order packets.
+            let diff = data_framing.sequence_number().wrapping_sub(source.sequence_number) as i8;
+            if diff <= 0 && diff > -20 {
+                return None;
+            }
+        }
+
+        let dmp = data_framing.dmp();
+        if dmp.start_code_slot() != NULL_START_CODE {
+            return None;
+        }
+
+        let mut data = [0; MAX_UNIVERSE_SIZE];
+        let slots = dmp.data_slots();
+        let len = slots.len().min(MAX_UNIVERSE_SIZE);
+        data[..len].copy_from_slice(&slots[..len]);
+
+        sources.insert(
+            cid,
+            ReceivedSource {
+                name: data_framing.source_name().to_string(),
+                priority: data_framing.priority(),
+                data,
+                last_seen: Instant::now(),
+                sequence_number: data_framing.sequence_number(),
+            },
+        );
+
+        Some(universe)
+    }
+
+    fn handle_discovery(
+        &mut self,
+        cid: ComponentIdentifier,
+        discovery_framing: &DiscoveryFraming,
+    ) -> Option<UniverseNumber> {
+        if !self.universes.contains(&DISCOVERY_UNIVERSE) {
+            return None;
+        }
+
+        let universe_discovery = discovery_framing.universe_discovery();
+        let source = self.discovered_sources.entry(cid).or_insert_with(|| DiscoveredSource {
+            name: String::new(),
+            last_seen: Instant::now(),
+            pages: BTreeMap::new(),
+        });
+
+        source.name = discovery_framing.source_name().to_string();
+        source.last_seen = Instant::now();
+        // Drop pages that no longer exist when a source's universe list shrinks.
+        source.pages.retain(|&page, _| page <= universe_discovery.last());
+        source
+            .pages
+            .insert(universe_discovery.page(), universe_discovery.list_of_universes().to_vec());
+
+        Some(DISCOVERY_UNIVERSE)
+    }
+
+    fn remove_lost_sources(&mut self) -> Option<UniverseNumber> {
+        let mut changed = None;
+
+        let len = self.discovered_sources.len();
+        self.discovered_sources.retain(|_, source| source.last_seen.elapsed() < DISCOVERY_TIMEOUT);
+        if self.discovered_sources.len() != len {
+            changed = Some(DISCOVERY_UNIVERSE);
+        }
+
+        for (universe, sources) in &mut self.sources {
+            let len = sources.len();
+            sources.retain(|_, source| source.last_seen.elapsed() < NETWORK_DATA_LOSS_TIMEOUT);
+            if sources.len() != len {
+                changed = Some(*universe);
+            }
+        }
+        changed
+    }
+}
+
+/// Configuration for a [Receiver].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiverConfig {
+    /// Port number the receiver listens on.
+    pub port: u16,
+    /// Local address of the network interface to join multicast groups on.
+    ///
+    /// When `None`, the operating system picks the interface.
+    pub interface: Option<Ipv4Addr>,
+}
+
+impl Default for ReceiverConfig {
+    fn default() -> Self {
+        Self { port: DEFAULT_PORT, interface: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::protocol::sacn::Universe;
+    use crate::output::protocol::sacn::source::{Source, SourceConfig};
+
+    fn receive(receiver: &mut Receiver) {
+        while receiver.recv(Duration::from_millis(100)).unwrap().is_some() {}
+    }
+
+    fn source(port: u16, priority: u8) -> Source {
+        Source::new(SourceConfig {
+            destinations: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, port))],
+            priority,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn universe(values: &[u8]) -> Universe {
+        let mut universe = Universe::new(1);
+        universe.data_slots = values.iter().copied().collect();
+        universe
+    }
+
+    #[test]
+    fn merge_over_loopback() {
+        let mut receiver = Receiver::new(ReceiverConfig { port: 0, interface: None }).unwrap();
+        // Multicast might not be available, but unicast data is still received.
+        receiver.join_universe(1).ok();
+        let port = receiver.socket_port().unwrap();
+
+        let low = source(port, 100);
+        let high = source(port, 150);
+
+        low.send_universe_data_packet(universe(&[255, 10])).unwrap();
+        high.send_universe_data_packet(universe(&[0, 20])).unwrap();
+        receive(&mut receiver);
+
+        assert_eq!(receiver.sources(1).count(), 2);
+        assert_eq!(receiver.merge(1, MergeMode::Htp).unwrap()[..3], [255, 20, 0]);
+        assert_eq!(receiver.merge(1, MergeMode::Priority).unwrap()[..3], [0, 20, 0]);
+
+        high.terminate_universe(universe(&[])).unwrap();
+        receive(&mut receiver);
+
+        assert_eq!(receiver.sources(1).count(), 1);
+        assert_eq!(receiver.merge(1, MergeMode::Priority).unwrap()[..3], [255, 10, 0]);
+        assert_eq!(receiver.merge(2, MergeMode::Priority), None);
+    }
+
+    #[test]
+    fn ignore_sources() {
+        let mut receiver = Receiver::new(ReceiverConfig { port: 0, interface: None }).unwrap();
+        receiver.join_universe(1).ok();
+        let port = receiver.socket_port().unwrap();
+
+        let own = source(port, 100);
+        let other = source(port, 100);
+        own.send_universe_data_packet(universe(&[255])).unwrap();
+        receive(&mut receiver);
+        assert_eq!(receiver.sources(1).count(), 1);
+
+        receiver.set_ignored_sources(HashSet::from([own.config().cid]));
+        assert_eq!(receiver.sources(1).count(), 0);
+
+        other.send_universe_data_packet(universe(&[10])).unwrap();
+        own.send_universe_data_packet(universe(&[255])).unwrap();
+        receive(&mut receiver);
+        assert_eq!(receiver.merge(1, MergeMode::Htp).unwrap()[..2], [10, 0]);
+    }
+
+    #[test]
+    fn discovery_over_loopback() {
+        let mut receiver = Receiver::new(ReceiverConfig { port: 0, interface: None }).unwrap();
+        receiver.join_discovery().ok();
+        let port = receiver.socket_port().unwrap();
+
+        let source = source(port, 100);
+        source.send_universe_discovery(&[3, 1, 2]).unwrap();
+        receive(&mut receiver);
+
+        let (cid, discovered) = receiver.discovered_sources().next().unwrap();
+        assert_eq!(*cid, source.config().cid);
+        assert_eq!(discovered.name, "New sACN Source");
+        assert_eq!(discovered.universes().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+}
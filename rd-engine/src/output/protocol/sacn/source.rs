@@ -2,8 +2,10 @@
 //!
 //! Responsible for sending sACN packets.
 
-use super::packet::{DataFraming, Dmp, Packet, PacketError, Pdu};
-use super::{ComponentIdentifier, DEFAULT_PORT, Universe};
+use super::packet::{
+    DataFraming, DiscoveryFraming, Dmp, Packet, PacketError, Pdu, SyncFraming, UniverseDiscovery,
+};
+use super::{ComponentIdentifier, DEFAULT_PORT, DISCOVERY_UNIVERSE, Universe, UniverseNumber};
 use socket2::{Domain, SockAddr, Socket, Type};
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr};
@@ -12,6 +14,8 @@ use std::time::{Duration, Instant};
 
 const DMX_SEND_INTERVAL: Duration = Duration::from_millis(44);
 const UNIVERSE_DISCOVERY_INTERVAL: Duration = Duration::from_secs(10);
+/// E1.31 6.2.6: A terminated stream is announced with three consecutive packets.
+const STREAM_TERMINATED_PACKET_COUNT: usize = 3;
 
 /// Error type returned by a [Source].
 #[derive(Debug, thiserror::Error)]
@@ -32,23 +36,40 @@ pub struct Source {
     config: SourceConfig,
 
     socket: Socket,
-    addr: SockAddr,
     sequence_numbers: Mutex<HashMap<u16, u8>>,
+    sync_sequence_number: Mutex<u8>,
     last_universe_discovery_time: Mutex<Option<Instant>>,
 }
 
 impl Source {
     /// Creates a new [Source].
     pub fn new(config: SourceConfig) -> Result<Self, SourceError> {
-        let domain = if config.ip.is_ipv4() { Domain::IPV4 } else { Domain::IPV6 };
+        let is_ipv6 = match config.bind_address {
+            Some(bind_address) => bind_address.is_ipv6(),
+            None => config.destinations.first().is_some_and(|addr| addr.is_ipv6()),
+        };
+        let domain = if is_ipv6 { Domain::IPV6 } else { Domain::IPV4 };
         let socket = Socket::new(domain, Type::DGRAM, None)?;
-        let addr: SockAddr = SocketAddr::new(config.ip, config.port).into();
+
+        if let Some(bind_address) = config.bind_address {
+            socket.bind(&SocketAddr::new(bind_address, 0).into())?;
+            if let IpAddr::V4(interface) = bind_address {
+                socket.set_multicast_if_v4(&interface)?;
+            }
+        }
+
+        if config.destinations.is_empty() && is_ipv6 {
+            return Err(SourceError::Io(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "sACN multicast is only supported over IPv4",
+            )));
+        }
 
         Ok(Source {
             config,
             socket,
-            addr,
             sequence_numbers: Mutex::new(HashMap::new()),
+            sync_sequence_number: Mutex::new(0),
             last_universe_discovery_time: Mutex::new(None),
         })
     }
@@ -78,6 +99,71 @@ impl Source {
     }
 
     pub fn send_universe_data_packet(&self, universe: Universe) -> Result<(), SourceError> {
+        self.send_data_packet(&universe, false)
+    }
+
+    /// Tells receivers to stop using the data of the universe immediately,
+    /// instead of waiting for the data loss timeout.
+    pub fn terminate_universe(&self, universe: Universe) -> Result<(), SourceError> {
+        for _ in 0..STREAM_TERMINATED_PACKET_COUNT {
+            self.send_data_packet(&universe, true)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a synchronization packet to the configured synchronization address.
+    ///
+    /// Does nothing when synchronization is disabled (address `0`).
+    pub fn send_sync_packet(&self) -> Result<(), SourceError> {
+        let synchronization_address = self.config.synchronization_address;
+        if synchronization_address == 0 {
+            return Ok(());
+        }
+
+        let sequence_number = {
+            let mut sequence_number = self.sync_sequence_number.lock().unwrap();
+            *sequence_number = sequence_number.wrapping_add(1);
+            *sequence_number
+        };
+
+        let sync_framing = SyncFraming::new(sequence_number, synchronization_address);
+        let packet = Packet::new(self.config.cid, Pdu::SyncFraming(sync_framing));
+        self.send(&packet.encode(), synchronization_address)
+    }
+
+    /// Announces the universes this source sends on the discovery universe, when due.
+    pub fn send_universe_discovery(&self, universes: &[UniverseNumber]) -> Result<(), SourceError> {
+        {
+            let mut last_time = self.last_universe_discovery_time.lock().unwrap();
+            if last_time.is_some_and(|time| time.elapsed() < UNIVERSE_DISCOVERY_INTERVAL) {
+                return Ok(());
+            }
+            *last_time = Some(Instant::now());
+        }
+
+        let mut universes = universes.to_vec();
+        universes.sort();
+        universes.dedup();
+
+        // E1.31 8.4: Each page lists up to 512 universes.
+        let pages = universes.chunks(512).collect::<Vec<_>>();
+        let last = pages.len().saturating_sub(1) as u8;
+        for (page, universes) in pages.iter().enumerate() {
+            let universe_discovery = UniverseDiscovery::new(page as u8, last, universes.to_vec());
+            let discovery_framing =
+                DiscoveryFraming::from_source_config(&self.config, universe_discovery)?;
+            let packet = Packet::new(self.config.cid, Pdu::DiscoveryFraming(discovery_framing));
+            self.send(&packet.encode(), DISCOVERY_UNIVERSE)?;
+        }
+
+        Ok(())
+    }
+
+    fn send_data_packet(
+        &self,
+        universe: &Universe,
+        stream_terminated: bool,
+    ) -> Result<(), SourceError> {
         let sequence_number = self.next_sequence_number_for_universe(universe.number);
 
         let packet = {
@@ -85,7 +171,7 @@ impl Source {
             let data_framing = DataFraming::from_source_config(
                 &self.config,
                 sequence_number,
-                false,
+                stream_terminated,
                 universe.number,
                 dmp,
             )?;
@@ -93,8 +179,19 @@ impl Source {
             Packet::new(self.config.cid, pdu)
         };
 
-        let bytes = packet.encode();
-        self.socket.send_to(&bytes, &self.addr)?;
+        self.send(&packet.encode(), universe.number)
+    }
+
+    /// Sends to the unicast destinations, or multicasts to the given universe when there are none.
+    fn send(&self, bytes: &[u8], universe: UniverseNumber) -> Result<(), SourceError> {
+        if self.config.destinations.is_empty() {
+            let addr = SocketAddr::new(multicast_address(universe).into(), self.config.port);
+            self.socket.send_to(bytes, &addr.into())?;
+        } else {
+            for addr in &self.config.destinations {
+                self.socket.send_to(bytes, &SockAddr::from(*addr))?;
+            }
+        }
 
         Ok(())
     }
@@ -108,6 +205,12 @@ impl Source {
     }
 }
 
+/// The IPv4 multicast address a universe is sent to: `239.255.{hi}.{lo}`.
+pub fn multicast_address(universe: UniverseNumber) -> Ipv4Addr {
+    let [hi, lo] = universe.to_be_bytes();
+    Ipv4Addr::new(239, 255, hi, lo)
+}
+
 impl Drop for Source {
     fn drop(&mut self) {
         self.shutdown().ok();
@@ -122,10 +225,16 @@ pub struct SourceConfig {
     /// Name of the source.
     pub name: String,
 
-    /// IP address the source should send to.
-    pub ip: IpAddr,
-    /// Port number the source should send to.
+    /// Unicast addresses the source should send to.
+    ///
+    /// When empty, each universe is multicast to its [multicast_address].
+    pub destinations: Vec<SocketAddr>,
+    /// Port number multicast data is sent to.
     pub port: u16,
+    /// Local address of the network interface to send from.
+    ///
+    /// When `None`, the operating system picks the interface.
+    pub bind_address: Option<IpAddr>,
 
     /// The priority of the data packets sent by the source.
     pub priority: u8,
@@ -136,6 +245,8 @@ pub struct SourceConfig {
     /// applications and shall not be used to generate live output.
     pub preview_data: bool,
     /// The synchronization universe of the source.
+    ///
+    /// When `0`, data is not synchronized.
     pub synchronization_address: u16,
     /// Indicates whether to lock or revert to an
     /// unsynchronized state when synchronization is lost.
@@ -156,8 +267,9 @@ impl Default for SourceConfig {
             cid: ComponentIdentifier::new_v4(),
             name: "New sACN Source".to_string(),
 
-            ip: Ipv4Addr::UNSPECIFIED.into(),
+            destinations: Vec::new(),
             port: DEFAULT_PORT,
+            bind_address: None,
 
             priority: 100,
             preview_data: false,
@@ -0,0 +1,158 @@
+//! DMX512 over a plain serial port.
+//!
+//! Cheap USB-DMX dongles expose the UART directly, so the host has to generate the
+//! break and mark after break that start every DMX frame.
+
+use std::io::{self, Write as _};
+use std::time::Duration;
+
+use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use crate::dmx::{MAX_UNIVERSE_SIZE, NULL_START_CODE};
+
+/// The DMX512 line rate.
+pub const BAUD_RATE: u32 = 250_000;
+
+/// A single null byte holds the line low for the start bit and eight data bits.
+const BREAK_BITS: u32 = 9;
+
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How the break at the start of a frame is generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakMode {
+    /// Holds the line low with the break ioctl of the serial driver.
+    #[default]
+    Ioctl,
+    /// Sends a null byte at a lower baud rate, for drivers that don't support the
+    /// break ioctl or can't time it precisely.
+    BaudRate,
+}
+
+/// The timing of the start of a DMX frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// The duration of the break. DMX512 requires at least 92 microseconds.
+    pub break_time: Duration,
+    /// The duration of the mark after break. DMX512 requires at least 12 microseconds.
+    pub mark_after_break: Duration,
+}
+
+impl Default for FrameTiming {
+    fn default() -> Self {
+        Self { break_time: Duration::from_micros(176), mark_after_break: Duration::from_micros(16) }
+    }
+}
+
+/// Sends DMX frames through a serial port.
+pub struct DmxPort {
+    port: Box<dyn SerialPort>,
+    break_mode: BreakMode,
+    timing: FrameTiming,
+    sleeper: spin_sleep::SpinSleeper,
+}
+
+impl DmxPort {
+    /// Opens the serial port at `path`.
+    pub fn open(path: &str, break_mode: BreakMode, timing: FrameTiming) -> io::Result<Self> {
+        let port = serialport::new(path, BAUD_RATE).timeout(WRITE_TIMEOUT).open()?;
+        Self::new(port, break_mode, timing)
+    }
+
+    /// Configures `port` for DMX512, 250 kbaud 8N2.
+    pub fn new(
+        mut port: Box<dyn SerialPort>,
+        break_mode: BreakMode,
+        timing: FrameTiming,
+    ) -> io::Result<Self> {
+        port.set_baud_rate(BAUD_RATE)?;
+        port.set_data_bits(DataBits::Eight)?;
+        port.set_stop_bits(StopBits::Two)?;
+        port.set_parity(Parity::None)?;
+        port.set_flow_control(FlowControl::None)?;
+        port.clear_break()?;
+
+        Ok(Self { port, break_mode, timing, sleeper: spin_sleep::SpinSleeper::default() })
+    }
+
+    /// Sends a frame with the null start code. Blocks until the frame is transmitted,
+    /// so the break of the next frame doesn't cut it off.
+    pub fn send_frame(&mut self, data: &[u8]) -> io::Result<()> {
+        self.send_break()?;
+
+        let data = &data[..data.len().min(MAX_UNIVERSE_SIZE)];
+        let mut frame = Vec::with_capacity(1 + data.len());
+        frame.push(NULL_START_CODE);
+        frame.extend(data);
+
+        self.port.write_all(&frame)?;
+        self.port.flush()
+    }
+
+    fn send_break(&mut self) -> io::Result<()> {
+        match self.break_mode {
+            BreakMode::Ioctl => {
+                self.port.set_break()?;
+                self.sleeper.sleep(self.timing.break_time);
+                self.port.clear_break()?;
+            }
+            BreakMode::BaudRate => {
+                self.port.set_baud_rate(self.break_baud_rate())?;
+                self.port.write_all(&[0])?;
+                self.port.flush()?;
+                self.port.set_baud_rate(BAUD_RATE)?;
+            }
+        }
+
+        self.sleeper.sleep(self.timing.mark_after_break);
+        Ok(())
+    }
+
+    /// The baud rate at which a null byte takes the configured break time.
+    fn break_baud_rate(&self) -> u32 {
+        let micros = self.timing.break_time.as_micros().max(1) as u32;
+        (BREAK_BITS * 1_000_000 / micros).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use serialport::TTYPort;
+
+    use super::*;
+
+    fn read_frame(port: &mut TTYPort, len: usize) -> Vec<u8> {
+        let mut received = vec![0; len];
+        port.set_timeout(Duration::from_secs(1)).unwrap();
+        port.read_exact(&mut received).unwrap();
+        received
+    }
+
+    #[test]
+    fn send_frame_over_pty() {
+        let (master, mut slave) = TTYPort::pair().unwrap();
+        let mut port =
+            DmxPort::new(Box::new(master), BreakMode::Ioctl, FrameTiming::default()).unwrap();
+
+        port.send_frame(&[1, 2, 3]).unwrap();
+        port.send_frame(&[4]).unwrap();
+
+        assert_eq!(read_frame(&mut slave, 6), vec![0, 1, 2, 3, 0, 4]);
+    }
+
+    #[test]
+    fn send_frame_with_baud_rate_break() {
+        let (master, mut slave) = TTYPort::pair().unwrap();
+        let mut port =
+            DmxPort::new(Box::new(master), BreakMode::BaudRate, FrameTiming::default()).unwrap();
+
+        // 9 bits at 51136 baud take 176 microseconds.
+        assert_eq!(port.break_baud_rate(), 51_136);
+
+        // The break shows up as a null byte on the other side.
+        port.send_frame(&[1, 2]).unwrap();
+        assert_eq!(read_frame(&mut slave, 4), vec![0, 0, 1, 2]);
+    }
+}
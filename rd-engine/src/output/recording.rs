@@ -0,0 +1,324 @@
+//! Recording and playback of output frames.
+//!
+//! A recording starts with a header, followed by frames. Each frame holds the time since
+//! the previous frame and only the universes that changed since then, as a list of
+//! changed channels. All integers except universe ids are LEB128 encoded.
+
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::dmx::{self, Multiverse, Universe, UniverseId, Value};
+
+const MAGIC: [u8; 4] = *b"RDMX";
+const VERSION: u8 = 1;
+
+const UNIVERSE_REMOVED: u8 = 0;
+const UNIVERSE_CHANGED: u8 = 1;
+
+/// Writes frames to a recording.
+pub struct Recorder<W: Write> {
+    writer: W,
+    last_time: Duration,
+    previous: Multiverse,
+    has_frames: bool,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Self {
+            writer,
+            last_time: Duration::ZERO,
+            previous: Multiverse::new(),
+            has_frames: false,
+        })
+    }
+
+    /// Records `multiverse` as sent at `time` since the start of the recording.
+    ///
+    /// Frames without changes are skipped, except for the first one.
+    pub fn record(&mut self, time: Duration, multiverse: &Multiverse) -> io::Result<()> {
+        let mut changes = Vec::new();
+        let mut count = 0;
+
+        for (id, _) in self.previous.universes() {
+            if !multiverse.has_universe(id) {
+                changes.extend(id.as_u16().to_le_bytes());
+                changes.push(UNIVERSE_REMOVED);
+                count += 1;
+            }
+        }
+
+        let empty = Universe::new();
+        for (id, universe) in multiverse.universes() {
+            let previous = self.previous.universe(id);
+            if previous == Some(universe) {
+                continue;
+            }
+
+            changes.extend(id.as_u16().to_le_bytes());
+            changes.push(UNIVERSE_CHANGED);
+            encode_changes(&mut changes, previous.unwrap_or(&empty), universe);
+            count += 1;
+        }
+
+        if count == 0 && self.has_frames {
+            return Ok(());
+        }
+
+        let delta = time.saturating_sub(self.last_time);
+        write_varint(&mut self.writer, delta.as_micros() as u64)?;
+        write_varint(&mut self.writer, count as u64)?;
+        self.writer.write_all(&changes)?;
+
+        self.last_time = time;
+        self.previous = multiverse.clone();
+        self.has_frames = true;
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// A frame read from a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedFrame {
+    /// The time since the start of the recording.
+    pub time: Duration,
+    pub multiverse: Multiverse,
+}
+
+/// Reads the frames of a recording.
+pub struct Player<R: Read> {
+    reader: R,
+    time: Duration,
+    current: Multiverse,
+}
+
+impl<R: Read> Player<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(invalid_data("not a DMX recording"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid_data(format!("unsupported recording version {}", header[4])));
+        }
+
+        Ok(Self { reader, time: Duration::ZERO, current: Multiverse::new() })
+    }
+
+    /// Reads the next frame, or `None` at the end of the recording.
+    pub fn next_frame(&mut self) -> io::Result<Option<RecordedFrame>> {
+        let Some(delta) = read_varint_or_eof(&mut self.reader)? else {
+            return Ok(None);
+        };
+        let count = read_varint(&mut self.reader)?;
+
+        for _ in 0..count {
+            let mut header = [0; 3];
+            self.reader.read_exact(&mut header)?;
+            let id = UniverseId::new(u16::from_le_bytes([header[0], header[1]]))
+                .map_err(|err| invalid_data(err.to_string()))?;
+
+            match header[2] {
+                UNIVERSE_REMOVED => {
+                    self.current.remove_universe(&id);
+                }
+                UNIVERSE_CHANGED => {
+                    if !self.current.has_universe(&id) {
+                        self.current.create_universe(id, Universe::new());
+                    }
+                    let universe = self.current.universe_mut(&id).unwrap();
+                    decode_changes(&mut self.reader, universe)?;
+                }
+                other => return Err(invalid_data(format!("unknown universe tag {other}"))),
+            }
+        }
+
+        self.time += Duration::from_micros(delta);
+        Ok(Some(RecordedFrame { time: self.time, multiverse: self.current.clone() }))
+    }
+}
+
+impl<R: Read> Iterator for Player<R> {
+    type Item = io::Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+/// Plays a recording back in real time.
+pub(crate) struct Playback<R: Read> {
+    player: Player<R>,
+    start: Instant,
+    next: Option<RecordedFrame>,
+    current: Multiverse,
+    ended: bool,
+}
+
+impl<R: Read> Playback<R> {
+    pub fn new(mut player: Player<R>) -> io::Result<Self> {
+        let next = player.next_frame()?;
+        Ok(Self { player, start: Instant::now(), next, current: Multiverse::new(), ended: false })
+    }
+
+    /// The frame that should be sent now, or `None` once the recording has ended.
+    pub fn current(&mut self) -> io::Result<Option<&Multiverse>> {
+        let elapsed = self.start.elapsed();
+        while let Some(frame) = self.next.take_if(|frame| frame.time <= elapsed) {
+            self.current = frame.multiverse;
+            self.next = self.player.next_frame()?;
+        }
+
+        // Send the last frame once before ending.
+        if self.next.is_none() {
+            if self.ended {
+                return Ok(None);
+            }
+            self.ended = true;
+        }
+        Ok(Some(&self.current))
+    }
+}
+
+fn encode_changes(bytes: &mut Vec<u8>, previous: &Universe, universe: &Universe) {
+    let changes = previous
+        .values()
+        .iter()
+        .zip(universe.values())
+        .enumerate()
+        .filter(|(_, (previous, value))| previous != value)
+        .map(|(index, (_, value))| (index, *value))
+        .collect::<Vec<_>>();
+
+    write_varint(bytes, changes.len() as u64).expect("writing to a Vec can't fail");
+    let mut next_index = 0;
+    for (index, value) in changes {
+        write_varint(bytes, (index - next_index) as u64).expect("writing to a Vec can't fail");
+        bytes.push(value.as_u8());
+        next_index = index + 1;
+    }
+}
+
+fn decode_changes(reader: &mut impl Read, universe: &mut Universe) -> io::Result<()> {
+    let count = read_varint(reader)?;
+    let mut next_index = 0;
+    for _ in 0..count {
+        let index = usize::try_from(read_varint(reader)?)
+            .ok()
+            .and_then(|gap| gap.checked_add(next_index))
+            .unwrap_or(usize::MAX);
+        if index >= dmx::MAX_UNIVERSE_SIZE {
+            return Err(invalid_data(format!("channel index {index} out of range")));
+        }
+
+        let mut value = [0];
+        reader.read_exact(&mut value)?;
+        universe.values_mut()[index] = Value(value[0]);
+        next_index = index + 1;
+    }
+    Ok(())
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<u64> {
+    read_varint_or_eof(reader)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+/// Reads a varint, or returns `None` if the reader is at its end.
+fn read_varint_or_eof(reader: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        if reader.read(&mut byte)? == 0 {
+            if shift == 0 {
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        value |= u64::from(byte[0] & 0x7F) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(invalid_data("varint too long"))
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dmx::{Address, Channel};
+
+    fn set(multiverse: &mut Multiverse, universe: u16, channel: u16, value: u8) {
+        let address =
+            Address::new(UniverseId::new(universe).unwrap(), Channel::new(channel).unwrap());
+        multiverse.set_value(&address, Value(value));
+    }
+
+    #[test]
+    fn record_and_play() {
+        let mut frames = Vec::new();
+        let mut multiverse = Multiverse::new();
+        set(&mut multiverse, 1, 1, 255);
+        set(&mut multiverse, 1, 512, 10);
+        frames.push(multiverse.clone());
+
+        // Unchanged frames are skipped.
+        frames.push(multiverse.clone());
+
+        set(&mut multiverse, 2, 300, 1);
+        frames.push(multiverse.clone());
+
+        multiverse.remove_universe(&UniverseId::new(1).unwrap());
+        frames.push(multiverse.clone());
+
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        for (ix, frame) in frames.iter().enumerate() {
+            recorder.record(Duration::from_millis(ix as u64 * 25), frame).unwrap();
+        }
+        let bytes = recorder.finish().unwrap();
+        // The header, then 11, 11 and 7 bytes for the recorded frames.
+        assert_eq!(bytes.len(), 5 + 11 + 11 + 7);
+
+        let played =
+            Player::new(bytes.as_slice()).unwrap().collect::<io::Result<Vec<_>>>().unwrap();
+        let times = played.iter().map(|frame| frame.time.as_millis()).collect::<Vec<_>>();
+        assert_eq!(times, [0, 50, 75]);
+        assert_eq!(played[0].multiverse, frames[0]);
+        assert_eq!(played[1].multiverse, frames[2]);
+        assert_eq!(played[2].multiverse, frames[3]);
+    }
+
+    #[test]
+    fn reject_invalid_recordings() {
+        assert!(Player::new(&b"nope!"[..]).is_err());
+
+        let mut bytes = Recorder::new(Vec::new()).unwrap().finish().unwrap();
+        bytes.extend([0, 1, 1, 0, UNIVERSE_CHANGED, 1]);
+        let mut player = Player::new(bytes.as_slice()).unwrap();
+        assert_eq!(player.next_frame().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
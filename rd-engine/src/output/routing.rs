@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+
+use crate::dmx::{Address, Channel, Multiverse, Universe, UniverseId, Value};
+use crate::output::{OutputChannelLimitDefinition, OutputRouteDefinition, OutputRoutingDefinition};
+
+/// Turns the internal [Multiverse] into the one the output instances send, as
+/// described by an [OutputRoutingDefinition].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Router {
+    routes: Vec<OutputRouteDefinition>,
+    limits: Vec<OutputChannelLimitDefinition>,
+    routed_universe_ids: HashSet<UniverseId>,
+}
+
+impl Router {
+    pub fn new(definition: &OutputRoutingDefinition) -> Self {
+        Self {
+            routes: definition.routes.clone(),
+            limits: definition.limits.clone(),
+            routed_universe_ids: definition.routes.iter().map(|r| r.source_universe_id).collect(),
+        }
+    }
+
+    pub fn route(&self, multiverse: Multiverse) -> Multiverse {
+        if self.routes.is_empty() && self.limits.is_empty() {
+            return multiverse;
+        }
+
+        let mut output = Multiverse::new();
+
+        for (id, universe) in multiverse.universes() {
+            if !self.routed_universe_ids.contains(id) {
+                merge_htp(&mut output, *id, 0, universe.values(), Channel::MAX.as_u16());
+            }
+        }
+
+        for route in &self.routes {
+            let Some(source) = multiverse.universe(&route.source_universe_id) else {
+                continue;
+            };
+
+            let values = &source.values()[route.source_channel.as_index()..];
+            merge_htp(
+                &mut output,
+                route.target_universe_id,
+                route.target_channel.as_index(),
+                values,
+                route.count,
+            );
+        }
+
+        for limit in &self.limits {
+            let Some(universe) = output.universe_mut(&limit.universe_id) else {
+                continue;
+            };
+
+            let start = limit.channel.as_index();
+            let end = (start + usize::from(limit.count)).min(Channel::MAX.as_index() + 1);
+            for value in &mut universe.values_mut()[start..end] {
+                *value = (*value).min(Value(limit.limit));
+            }
+        }
+
+        output
+    }
+
+    /// Maps internal addresses to the output addresses they end up on.
+    pub fn route_addresses(&self, addresses: &HashSet<Address>) -> HashSet<Address> {
+        if self.routes.is_empty() {
+            return addresses.clone();
+        }
+
+        let mut output = HashSet::new();
+        for address in addresses {
+            if !self.routed_universe_ids.contains(&address.universe()) {
+                output.insert(*address);
+            }
+
+            for route in self.routes.iter().filter(|r| r.source_universe_id == address.universe()) {
+                let offset = address.channel().as_u16().checked_sub(route.source_channel.as_u16());
+                let Some(offset) = offset.filter(|offset| *offset < route.count) else {
+                    continue;
+                };
+
+                if let Ok(channel) = Channel::new(route.target_channel.as_u16() + offset) {
+                    output.insert(Address::new(route.target_universe_id, channel));
+                }
+            }
+        }
+        output
+    }
+}
+
+fn merge_htp(
+    multiverse: &mut Multiverse,
+    universe_id: UniverseId,
+    start: usize,
+    values: &[Value],
+    count: u16,
+) {
+    if !multiverse.has_universe(&universe_id) {
+        multiverse.create_universe(universe_id, Universe::new());
+    }
+    let universe = multiverse.universe_mut(&universe_id).unwrap();
+
+    let targets = universe.values_mut()[start..].iter_mut();
+    for (target, value) in targets.zip(values).take(usize::from(count)) {
+        *target = (*target).max(*value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn universe_id(id: u16) -> UniverseId {
+        UniverseId::new(id).unwrap()
+    }
+
+    fn channel(channel: u16) -> Channel {
+        Channel::new(channel).unwrap()
+    }
+
+    fn route(
+        source: u16,
+        source_channel: u16,
+        target: u16,
+        target_channel: u16,
+        count: u16,
+    ) -> OutputRouteDefinition {
+        OutputRouteDefinition {
+            source_universe_id: universe_id(source),
+            source_channel: self::channel(source_channel),
+            target_universe_id: universe_id(target),
+            target_channel: self::channel(target_channel),
+            count,
+        }
+    }
+
+    fn value(multiverse: &Multiverse, universe: u16, channel: u16) -> Option<u8> {
+        let address = Address::new(universe_id(universe), self::channel(channel));
+        multiverse.value(&address).map(|v| v.as_u8())
+    }
+
+    #[test]
+    fn route_remap_merge_and_limit() {
+        let router = Router::new(&OutputRoutingDefinition {
+            routes: vec![route(1, 1, 10, 1, 512), route(1, 1, 20, 101, 2), route(2, 1, 10, 1, 512)],
+            limits: vec![OutputChannelLimitDefinition {
+                universe_id: universe_id(10),
+                channel: channel(3),
+                count: 1,
+                limit: 0,
+            }],
+        });
+
+        let mut multiverse = Multiverse::new();
+        for (universe, channel, value) in
+            [(1, 1, 100), (1, 2, 10), (1, 3, 255), (2, 2, 50), (3, 1, 7)]
+        {
+            multiverse.set_value(
+                &Address::new(universe_id(universe), self::channel(channel)),
+                Value(value),
+            );
+        }
+
+        let output = router.route(multiverse);
+
+        // Routed sources are not sent as-is, others are.
+        assert_eq!(value(&output, 1, 1), None);
+        assert_eq!(value(&output, 3, 1), Some(7));
+
+        // Universes 1 and 2 are merged HTP into 10, with channel 3 inhibited.
+        assert_eq!(value(&output, 10, 1), Some(100));
+        assert_eq!(value(&output, 10, 2), Some(50));
+        assert_eq!(value(&output, 10, 3), Some(0));
+
+        // Channels 1 and 2 of universe 1 are offset to 101 and 102 of universe 20.
+        assert_eq!(value(&output, 20, 101), Some(100));
+        assert_eq!(value(&output, 20, 102), Some(10));
+        assert_eq!(value(&output, 20, 103), Some(0));
+
+        let addresses = HashSet::from([
+            Address::new(universe_id(1), channel(2)),
+            Address::new(universe_id(3), channel(5)),
+        ]);
+        assert_eq!(
+            router.route_addresses(&addresses),
+            HashSet::from([
+                Address::new(universe_id(10), channel(2)),
+                Address::new(universe_id(20), channel(102)),
+                Address::new(universe_id(3), channel(5)),
+            ])
+        );
+    }
+}
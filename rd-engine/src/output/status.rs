@@ -0,0 +1,120 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use uuid::Uuid;
+
+use crate::output::OutputInstanceKind;
+
+/// The send rate is averaged over this window.
+const SEND_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub enum OutputInstanceState {
+    #[default]
+    Stopped,
+    Running,
+    /// The instance failed to start, or its last frame failed to send.
+    Error,
+}
+
+/// The health of a single output instance.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OutputInstanceStatus {
+    pub kind: OutputInstanceKind,
+    /// The index of the instance within the instances of its kind.
+    pub index: usize,
+    pub name: String,
+    /// The CID the instance identifies itself with, for sACN instances.
+    pub cid: Option<Uuid>,
+    pub state: OutputInstanceState,
+    pub last_error: Option<String>,
+    pub frames_sent: u64,
+    /// Frames per second.
+    pub send_rate: f32,
+    pub last_frame_time: Option<SystemTime>,
+}
+
+/// Tracks the health of an output instance, updated from its thread.
+#[derive(Debug, Default)]
+pub(crate) struct InstanceHealth {
+    inner: Mutex<InstanceHealthInner>,
+}
+
+#[derive(Debug, Default)]
+struct InstanceHealthInner {
+    state: OutputInstanceState,
+    last_error: Option<String>,
+    frames_sent: u64,
+    last_frame_time: Option<SystemTime>,
+    last_frame_instant: Option<Instant>,
+    window_start: Option<Instant>,
+    window_frames: u64,
+    send_rate: f32,
+}
+
+impl InstanceHealth {
+    pub fn started(&self) {
+        self.inner.lock().unwrap().state = OutputInstanceState::Running;
+    }
+
+    pub fn stopped(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = OutputInstanceState::Stopped;
+        inner.send_rate = 0.0;
+        inner.window_start = None;
+    }
+
+    pub fn frame_sent(&self) {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = OutputInstanceState::Running;
+        inner.frames_sent += 1;
+        inner.last_frame_time = Some(SystemTime::now());
+        inner.last_frame_instant = Some(now);
+
+        let window_start = *inner.window_start.get_or_insert(now);
+        inner.window_frames += 1;
+        let elapsed = now - window_start;
+        if elapsed >= SEND_RATE_WINDOW {
+            inner.send_rate = inner.window_frames as f32 / elapsed.as_secs_f32();
+            inner.window_start = Some(now);
+            inner.window_frames = 0;
+        }
+    }
+
+    pub fn error(&self, err: impl fmt::Display) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = OutputInstanceState::Error;
+        inner.last_error = Some(err.to_string());
+    }
+
+    pub fn status(
+        &self,
+        kind: OutputInstanceKind,
+        index: usize,
+        name: String,
+        cid: Option<Uuid>,
+    ) -> OutputInstanceStatus {
+        let inner = self.inner.lock().unwrap();
+
+        // The rate is only updated when frames are sent, so it goes stale when they stop.
+        let sending = inner
+            .last_frame_instant
+            .is_some_and(|instant| instant.elapsed() < SEND_RATE_WINDOW * 2);
+
+        OutputInstanceStatus {
+            kind,
+            index,
+            name,
+            cid,
+            state: inner.state,
+            last_error: inner.last_error.clone(),
+            frames_sent: inner.frames_sent,
+            send_rate: if sending { inner.send_rate } else { 0.0 },
+            last_frame_time: inner.last_frame_time,
+        }
+    }
+}
@@ -44,6 +44,24 @@ impl PipelineCache {
             })
     }
 
+    /// All DMX addresses that are driven by a patched fixture.
+    pub fn patched_addresses(&self) -> impl Iterator<Item = &dmx::Address> {
+        self.channel_functions.values().flat_map(|attrs| attrs.values()).flat_map(
+            |info| match &info.kind {
+                ChannelFunctionKind::Physical { addresses } => addresses.as_slice(),
+                ChannelFunctionKind::Virtual { .. } => &[],
+            },
+        )
+    }
+
+    pub fn channel_functions(
+        &self,
+    ) -> impl Iterator<Item = (&FixtureId, &AttributeName, &ChannelFunctionInfo)> {
+        self.channel_functions.iter().flat_map(|(fixture_id, attrs)| {
+            attrs.iter().map(move |(attribute, info)| (fixture_id, attribute, info))
+        })
+    }
+
     pub fn get(
         &self,
         fixture_id: &FixtureId,
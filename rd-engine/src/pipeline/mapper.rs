@@ -55,6 +55,43 @@ pub fn map(attributes: &AttributeValues, cache: &PipelineCache) -> Multiverse {
     multiverse
 }
 
+/// Decodes a [Multiverse] back into the [AttributeValues] that [map] turns into it.
+///
+/// Virtual channel functions have no DMX of their own and are left out. Attributes that
+/// share a DMX channel all get the value of that channel.
+pub fn unmap(multiverse: &Multiverse, cache: &PipelineCache) -> AttributeValues {
+    let mut attributes = AttributeValues::new();
+
+    for (fixture_id, attribute, info) in cache.channel_functions() {
+        let ChannelFunctionKind::Physical { addresses } = &info.kind else {
+            continue;
+        };
+
+        let Some(values) =
+            addresses.iter().map(|address| multiverse.value(address)).collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+
+        let Some(clamped) = ClampedValue::from_address_values(&values) else {
+            continue;
+        };
+
+        // Without a physical range the DMX value would be lost, so keep it clamped.
+        let value = match (info.min, info.max) {
+            (AttributeValue::Physical(min), AttributeValue::Physical(max)) if min != max => {
+                AttributeValue::Physical(
+                    AttributeValue::Clamped(clamped).to_physical_value(info.min, info.max),
+                )
+            }
+            _ => AttributeValue::Clamped(clamped),
+        };
+        attributes.set(*fixture_id, attribute.clone(), value);
+    }
+
+    attributes
+}
+
 fn set_value(
     fixture_id: &FixtureId,
     attribute: &AttributeName,
@@ -124,3 +161,30 @@ fn set_value(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{Project, dmx::Value, patch::Patch};
+
+    #[test]
+    fn unmap_round_trip() {
+        let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples/capital_inspired");
+        let project = Project::load_from_folder(example).unwrap();
+        let patch = Patch::new(project.patch().clone(), project.gdtfs().clone()).unwrap();
+        let cache = PipelineCache::new(&patch);
+
+        let mut multiverse = Multiverse::new();
+        for (ix, address) in cache.patched_addresses().enumerate() {
+            multiverse.set_value(address, Value((ix * 37 % 256) as u8));
+        }
+
+        let attributes = unmap(&multiverse, &cache);
+        assert!(
+            attributes.values().any(|(_, _, value)| matches!(value, AttributeValue::Physical(_)))
+        );
+        assert_eq!(map(&attributes, &cache), multiverse);
+    }
+}
@@ -84,6 +84,11 @@ impl Pipeline {
         }
     }
 
+    /// Decodes DMX, like the output of another console, into values for the patched fixtures.
+    pub fn unmap(&self, multiverse: &Multiverse) -> AttributeValues {
+        mapper::unmap(multiverse, &self.cache)
+    }
+
     pub fn cache(&self) -> &cache::PipelineCache {
         &self.cache
     }
@@ -8,6 +8,7 @@ use anyhow::Context as _;
 
 use crate::{
     Engine,
+    input::InputDefinition,
     mvr_gdtf::gdtf::{Gdtf, resource::ResourceKey},
     object::Objects,
     output::OutputDefinition,
@@ -18,6 +19,7 @@ use crate::{
 const RELATIVE_GDTF_FOLDER_PATH: &str = "gdtf/";
 const RELATIVE_PATCH_PATH: &str = "patch.json";
 const RELATIVE_OUTPUT_PATH: &str = "output.json";
+const RELATIVE_INPUT_PATH: &str = "input.json";
 const RELATIVE_TRIGGERS_PATH: &str = "triggers.json";
 const RELATIVE_OBJECTS_PATH: &str = "objects.json";
 
@@ -28,6 +30,7 @@ pub struct Project {
 
     patch: PatchDefinition,
     output: OutputDefinition,
+    input: InputDefinition,
     triggers: TriggersDefinition,
     objects: Objects,
 }
@@ -60,6 +63,18 @@ impl Project {
             .with_context(|| format!("Failed to parse output file: {}", output_path.display()))
             .inspect_err(|e| log::error!("{:?}", e))?;
 
+        // Projects without any input configured don't have an input file.
+        let input_path = path.join(RELATIVE_INPUT_PATH);
+        let input: InputDefinition = if input_path.exists() {
+            let input_str = std::fs::read_to_string(&input_path)
+                .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+            serde_json::from_str(&input_str)
+                .with_context(|| format!("Failed to parse input file: {}", input_path.display()))
+                .inspect_err(|e| log::error!("{:?}", e))?
+        } else {
+            InputDefinition::default()
+        };
+
         let triggers_path = path.join(RELATIVE_TRIGGERS_PATH);
         let triggers_str = std::fs::read_to_string(&triggers_path).with_context(|| {
             format!("Failed to read triggers file: {}", triggers_path.display())
@@ -77,7 +92,7 @@ impl Project {
 
         log::info!("Loaded Radiant Project");
 
-        Ok(Self { path: Some(path), patch, gdtfs, output, triggers, objects })
+        Ok(Self { path: Some(path), patch, gdtfs, output, input, triggers, objects })
     }
 
     pub fn load_from_engine(path: PathBuf, engine: &mut Engine) -> Self {
@@ -86,6 +101,7 @@ impl Project {
             gdtfs: engine.patch().gdtfs().clone(),
             patch: engine.patch().definition().clone(),
             output: engine.output_agent().definition().clone(),
+            input: engine.input_agent().definition().clone(),
             triggers: engine.triggers_agent().definition().clone(),
             objects: engine.objects().clone(),
         }
@@ -110,6 +126,12 @@ impl Project {
         std::fs::write(&output_path, output_str)
             .with_context(|| format!("Failed to write output file: {}", output_path.display()))?;
 
+        let input_path = path.join(RELATIVE_INPUT_PATH);
+        let input_str =
+            serde_json::to_string_pretty(&self.input).context("Failed to serialize input")?;
+        std::fs::write(&input_path, input_str)
+            .with_context(|| format!("Failed to write input file: {}", input_path.display()))?;
+
         let triggers_path = path.join(RELATIVE_TRIGGERS_PATH);
         let triggers_str =
             serde_json::to_string_pretty(&self.triggers).context("Failed to serialize triggers")?;
@@ -147,10 +169,26 @@ impl Project {
         &self.output
     }
 
+    pub fn set_output(&mut self, output: OutputDefinition) {
+        self.output = output;
+    }
+
+    pub fn input(&self) -> &InputDefinition {
+        &self.input
+    }
+
+    pub fn set_input(&mut self, input: InputDefinition) {
+        self.input = input;
+    }
+
     pub fn triggers(&self) -> &TriggersDefinition {
         &self.triggers
     }
 
+    pub fn set_triggers(&mut self, triggers: TriggersDefinition) {
+        self.triggers = triggers;
+    }
+
     pub fn objects(&self) -> &Objects {
         &self.objects
     }
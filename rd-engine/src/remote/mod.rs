@@ -0,0 +1,11 @@
+//! Remote-control API.
+//!
+//! Exposes the [EngineHandle](crate::EngineHandle) over a JSON protocol on
+//! WebSocket, so external devices can execute commands, query state and follow
+//! engine events.
+
+mod protocol;
+mod server;
+
+pub use protocol::*;
+pub use server::*;
@@ -0,0 +1,99 @@
+use crate::{
+    cmd::Command,
+    event::Event,
+    object::{ExecutorId, ObjectId, Objects},
+    patch::{FixtureId, PatchDefinition},
+    value::AttributeValues,
+};
+
+/// A message sent from a remote client to the server.
+///
+/// Every message carries an `id` which is echoed back in the matching
+/// [ServerMessage::Response] or [ServerMessage::Error].
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientMessage {
+    /// Authenticate the connection with the shared token.
+    ///
+    /// This has to be the first message sent over a connection.
+    Authenticate { id: u64, token: String },
+    /// Execute a [Command] on the engine.
+    Execute { id: u64, command: Command },
+    /// Read a part of the current engine state.
+    Query { id: u64, query: Query },
+    /// Start receiving [ServerMessage::Event]s.
+    Subscribe { id: u64 },
+    /// Stop receiving [ServerMessage::Event]s.
+    Unsubscribe { id: u64 },
+}
+
+impl ClientMessage {
+    /// The id of this message.
+    pub fn id(&self) -> u64 {
+        match self {
+            ClientMessage::Authenticate { id, .. }
+            | ClientMessage::Execute { id, .. }
+            | ClientMessage::Query { id, .. }
+            | ClientMessage::Subscribe { id }
+            | ClientMessage::Unsubscribe { id } => *id,
+        }
+    }
+}
+
+/// The part of the engine state requested by a [ClientMessage::Query].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum Query {
+    Patch,
+    Objects,
+    AttributeValues,
+    Programmer,
+    Selection,
+    Highlight,
+    Executors,
+}
+
+/// A message sent from the server to a remote client.
+#[derive(Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum ServerMessage {
+    /// A successful reply to the [ClientMessage] with the same id.
+    Response { id: u64, response: Response },
+    /// A failed reply to the [ClientMessage] with the same id.
+    ///
+    /// The id is `None` if the incoming message could not be parsed.
+    Error { id: Option<u64>, message: String },
+    /// An [Event] emitted by the engine. Only sent to subscribed clients.
+    Event { event: Event },
+}
+
+#[derive(Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum Response {
+    Authenticated,
+    Executed,
+    Subscribed,
+    Unsubscribed,
+    Patch { patch: PatchDefinition },
+    Objects { objects: Box<Objects> },
+    AttributeValues { values: AttributeValues },
+    Programmer { values: AttributeValues },
+    Selection { fixture_ids: Vec<FixtureId> },
+    Highlight { enabled: bool },
+    Executors { executors: Vec<ExecutorState> },
+}
+
+/// The playback state of a single executor.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ExecutorState {
+    pub executor_id: ExecutorId,
+    pub enabled: bool,
+    pub master: f32,
+    pub sequence: Option<ObjectId>,
+    pub cue_index: Option<usize>,
+    pub cue_name: Option<String>,
+}
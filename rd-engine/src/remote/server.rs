@@ -0,0 +1,346 @@
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::Context as _;
+use tungstenite::{Message, WebSocket};
+
+use crate::{
+    EngineHandle,
+    event::EventListener,
+    object::ExecutorContent,
+    remote::{ClientMessage, ExecutorState, Query, Response, ServerMessage},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Configuration for a [RemoteServer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteConfig {
+    /// The address the server listens on.
+    pub address: SocketAddr,
+    /// The shared token clients have to authenticate with.
+    pub token: String,
+}
+
+/// Serves the remote-control API over WebSocket.
+///
+/// Each connection gets its own thread that forwards commands and queries to
+/// the [EngineHandle], and optionally streams engine events back to the client.
+pub struct RemoteServer {
+    address: SocketAddr,
+
+    thread_handle: Option<JoinHandle<()>>,
+    thread_running: Arc<AtomicBool>,
+}
+
+impl RemoteServer {
+    pub fn start(config: RemoteConfig, engine: EngineHandle) -> anyhow::Result<Self> {
+        anyhow::ensure!(!config.token.is_empty(), "Remote token should not be empty");
+
+        let listener = TcpListener::bind(config.address)
+            .with_context(|| format!("Failed to bind remote server to {}", config.address))?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let token: Arc<str> = config.token.into();
+
+        let handle = thread::Builder::new()
+            .name("rd_remote".to_string())
+            .spawn({
+                let running = running.clone();
+                move || {
+                    while running.load(Ordering::SeqCst) {
+                        match listener.accept() {
+                            Ok((stream, peer)) => {
+                                let running = running.clone();
+                                let engine = engine.clone();
+                                let token = token.clone();
+                                let spawn_result = thread::Builder::new()
+                                    .name(format!("rd_remote_{peer}"))
+                                    .spawn(move || {
+                                        if let Err(err) =
+                                            handle_connection(stream, engine, &token, &running)
+                                        {
+                                            log::warn!("Remote connection {peer} closed: {err}");
+                                        }
+                                    });
+                                if let Err(err) = spawn_result {
+                                    log::error!("Failed to spawn remote connection thread: {err}");
+                                }
+                            }
+                            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                                thread::sleep(POLL_INTERVAL);
+                            }
+                            Err(err) => {
+                                log::error!("Failed to accept remote connection: {err}");
+                                thread::sleep(POLL_INTERVAL);
+                            }
+                        }
+                    }
+                }
+            })
+            .context("Failed to spawn remote server thread")?;
+
+        log::info!("Remote server listening on {address}");
+
+        Ok(Self { address, thread_handle: Some(handle), thread_running: running })
+    }
+
+    pub fn stop(&mut self) {
+        if self.thread_handle.is_some() {
+            self.thread_running.store(false, Ordering::SeqCst);
+            if let Some(handle) = self.thread_handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Session {
+    engine: EngineHandle,
+    authenticated: bool,
+    subscription: Option<EventListener>,
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    engine: EngineHandle,
+    token: &str,
+    running: &AtomicBool,
+) -> anyhow::Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut socket = tungstenite::accept(stream).context("WebSocket handshake failed")?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let mut session = Session { engine, authenticated: false, subscription: None };
+
+    while running.load(Ordering::SeqCst) {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let reply = session.handle_text(&text, token);
+                let close = matches!(reply, ServerMessage::Error { .. }) && !session.authenticated;
+                send(&mut socket, &reply)?;
+                if close {
+                    socket.close(None)?;
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => break,
+            Err(err) => return Err(err.into()),
+        }
+
+        if let Some(subscription) = &session.subscription {
+            let events = subscription.try_iter().collect::<Vec<_>>();
+            for event in events {
+                send(&mut socket, &ServerMessage::Event { event })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, message: &ServerMessage) -> anyhow::Result<()> {
+    let text = serde_json::to_string(message).context("Failed to serialize server message")?;
+    socket.send(Message::text(text))?;
+    Ok(())
+}
+
+impl Session {
+    fn handle_text(&mut self, text: &str, token: &str) -> ServerMessage {
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(err) => {
+                return ServerMessage::Error {
+                    id: None,
+                    message: format!("Invalid message: {err}"),
+                };
+            }
+        };
+
+        let id = message.id();
+        match self.handle_message(message, token) {
+            Ok(response) => ServerMessage::Response { id, response },
+            Err(err) => ServerMessage::Error { id: Some(id), message: err.to_string() },
+        }
+    }
+
+    fn handle_message(&mut self, message: ClientMessage, token: &str) -> anyhow::Result<Response> {
+        if let ClientMessage::Authenticate { token: client_token, .. } = &message {
+            anyhow::ensure!(tokens_match(token, client_token), "Invalid token");
+            self.authenticated = true;
+            return Ok(Response::Authenticated);
+        }
+
+        anyhow::ensure!(self.authenticated, "Not authenticated");
+
+        match message {
+            ClientMessage::Authenticate { .. } => unreachable!(),
+            ClientMessage::Execute { command, .. } => {
+                self.engine.execute(command)?;
+                Ok(Response::Executed)
+            }
+            ClientMessage::Query { query, .. } => Ok(self.query(query)),
+            ClientMessage::Subscribe { .. } => {
+                if self.subscription.is_none() {
+                    self.subscription = Some(self.engine.subscribe()?);
+                }
+                Ok(Response::Subscribed)
+            }
+            ClientMessage::Unsubscribe { .. } => {
+                self.subscription = None;
+                Ok(Response::Unsubscribed)
+            }
+        }
+    }
+
+    fn query(&self, query: Query) -> Response {
+        let snapshot = self.engine.snapshot();
+        match query {
+            Query::Patch => Response::Patch { patch: snapshot.patch().definition().clone() },
+            Query::Objects => {
+                Response::Objects { objects: Box::new((*snapshot.objects()).clone()) }
+            }
+            Query::AttributeValues => {
+                Response::AttributeValues { values: snapshot.pipeline().attribute_values().clone() }
+            }
+            Query::Programmer => {
+                Response::Programmer { values: snapshot.programmer().values().clone() }
+            }
+            Query::Selection => {
+                Response::Selection { fixture_ids: snapshot.selection().fixture_ids().to_vec() }
+            }
+            Query::Highlight => Response::Highlight { enabled: snapshot.highlight() },
+            Query::Executors => {
+                let objects = snapshot.objects();
+                let executors = objects
+                    .executors()
+                    .map(|(executor_id, executor)| {
+                        let (sequence, cue_index) = match executor.content() {
+                            Some(ExecutorContent::Sequence(sc)) => {
+                                (Some(sc.sequence()), Some(sc.cue_index()))
+                            }
+                            None => (None, None),
+                        };
+                        let cue_name =
+                            objects.current_cue(executor).map(|cue| cue.name().to_string());
+
+                        ExecutorState {
+                            executor_id,
+                            enabled: executor.enabled(),
+                            master: executor.master(),
+                            sequence,
+                            cue_index,
+                            cue_name,
+                        }
+                    })
+                    .collect();
+                Response::Executors { executors }
+            }
+        }
+    }
+}
+
+/// Compares tokens in constant time to avoid leaking how much of a guess was right.
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected.bytes().zip(given.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+
+    use serde_json::json;
+    use tungstenite::{Message, WebSocket, stream::MaybeTlsStream};
+
+    use super::*;
+    use crate::{Engine, Project};
+
+    type Client = WebSocket<MaybeTlsStream<TcpStream>>;
+
+    fn read(client: &mut Client) -> serde_json::Value {
+        loop {
+            if let Message::Text(text) = client.read().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    fn request(client: &mut Client, message: serde_json::Value) -> serde_json::Value {
+        client.send(Message::text(message.to_string())).unwrap();
+        loop {
+            let reply = read(client);
+            if reply["type"] != "Event" {
+                return reply;
+            }
+        }
+    }
+
+    #[test]
+    fn remote_session() {
+        let engine = EngineHandle::new(Engine::new(Project::new()).unwrap());
+        let config =
+            RemoteConfig { address: "127.0.0.1:0".parse().unwrap(), token: "secret".to_string() };
+        let server = RemoteServer::start(config, engine.clone()).unwrap();
+        let url = format!("ws://{}", server.address());
+
+        let (mut client, _) = tungstenite::connect(&url).unwrap();
+        let reply = request(
+            &mut client,
+            json!({ "type": "Query", "id": 1, "query": { "type": "Highlight" } }),
+        );
+        assert_eq!(reply["type"], "Error");
+
+        let (mut client, _) = tungstenite::connect(&url).unwrap();
+        let reply =
+            request(&mut client, json!({ "type": "Authenticate", "id": 1, "token": "secret" }));
+        assert_eq!(reply["response"]["type"], "Authenticated");
+
+        let reply = request(&mut client, json!({ "type": "Subscribe", "id": 2 }));
+        assert_eq!(reply["response"]["type"], "Subscribed");
+
+        let reply = request(
+            &mut client,
+            json!({ "type": "Execute", "id": 3, "command": { "type": "HighlightToggle" } }),
+        );
+        assert_eq!(reply["id"], 3);
+        assert_eq!(reply["response"]["type"], "Executed");
+
+        let reply = request(
+            &mut client,
+            json!({ "type": "Query", "id": 4, "query": { "type": "Highlight" } }),
+        );
+        assert_eq!(reply["response"]["enabled"], true);
+
+        loop {
+            let message = read(&mut client);
+            if message["event"]["type"] == "HighlightChanged" {
+                assert_eq!(message["event"]["enabled"], true);
+                break;
+            }
+        }
+
+        engine.shutdown().unwrap();
+    }
+}
@@ -1,22 +1,68 @@
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
-use midir::MidiInputConnection;
+use midir::{MidiInputConnection, MidiOutputConnection};
 
+use crate::event::Event;
+use crate::object::{ExecutorId, ObjectId, ObjectKind, Objects};
 use crate::trigger::{
-    MidiMessage, MidiTriggerDefinition, Trigger, TriggerTarget, TriggersDefinition,
+    MidiMessage, MidiTriggerDefinition, MscDefinition, MultiRange, Trigger, TriggerTarget,
+    TriggersDefinition,
+    midi::{self, MidiDecoder, MidiValue},
+    msc::{self, MscCommand, MscMessage},
+    osc::OscServer,
+    u7,
 };
 
+const MIDI_RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
 pub struct TriggersAgent {
     definition: TriggersDefinition,
+    trigger_tx: flume::Sender<Trigger>,
     trigger_rx: flume::Receiver<Trigger>,
-    _inner: Arc<TriggersAgentInner>,
+    midi_mappings: Arc<RwLock<Vec<MidiTriggerDefinition>>>,
+    msc: Arc<RwLock<Option<MscDefinition>>>,
+    midi_learn: Option<MidiLearn>,
+    midi_devices: Arc<Vec<MidiDeviceStatus>>,
+    midi_devices_rx: flume::Receiver<Vec<MidiDeviceStatus>>,
+    // NOTE: Dropping this stops the MIDI supervisor.
+    midi_rescan_tx: flume::Sender<()>,
+    osc_server: Option<OscServer>,
+    inner: Arc<TriggersAgentInner>,
 }
 
 struct TriggersAgentInner {
     // NOTE: These are stored here to keep them alive for as long as the resolver lives.
-    _midi_connections:
-        Mutex<Vec<MidiInputConnection<(Vec<MidiTriggerDefinition>, flume::Sender<Trigger>)>>>,
+    midi_connections: Mutex<HashMap<String, MidiInputConnection<MidiInputContext>>>,
+    midi_outputs: Mutex<HashMap<String, MidiOutputConnection>>,
+    /// Set while learning, so the input connections forward what they receive.
+    midi_learn_tx: MidiLearnSender,
+}
+
+struct MidiInputContext {
+    device_name: String,
+    decoder: MidiDecoder,
+    mappings: Arc<RwLock<Vec<MidiTriggerDefinition>>>,
+    msc: Arc<RwLock<Option<MscDefinition>>>,
+    trigger_tx: flume::Sender<Trigger>,
+    learn_tx: MidiLearnSender,
+}
+
+type MidiLearnSender = Arc<Mutex<Option<flume::Sender<(String, Vec<u8>)>>>>;
+
+struct MidiLearn {
+    target: TriggerTarget,
+    captured_rx: flume::Receiver<(String, Vec<u8>)>,
+}
+
+/// The connection state of a MIDI device used in the [TriggersDefinition].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MidiDeviceStatus {
+    pub device_name: String,
+    pub input_connected: bool,
+    pub output_connected: bool,
 }
 
 impl TriggersAgent {
@@ -24,102 +70,453 @@ impl TriggersAgent {
         log::debug!("Starting Triggers Agent...");
 
         let (trigger_tx, trigger_rx) = flume::bounded(512);
+        let (midi_rescan_tx, midi_rescan_rx) = flume::unbounded();
+        let (midi_devices_tx, midi_devices_rx) = flume::unbounded();
 
-        let unique_midi_device_names: HashSet<_> =
-            definition.midi().iter().map(|child| child.device_name()).collect();
+        let midi_mappings = Arc::new(RwLock::new(definition.midi().to_vec()));
+        let msc = Arc::new(RwLock::new(definition.msc().cloned()));
 
-        let mut devices_to_init = Vec::new();
-        for device_name in unique_midi_device_names {
-            let midi = definition
-                .midi()
-                .iter()
-                .filter(|midi_mapping| midi_mapping.device_name() == device_name)
-                .cloned()
-                .collect::<Vec<_>>();
+        let osc_server = definition.osc().and_then(|osc| {
+            OscServer::start(osc.clone(), trigger_tx.clone())
+                .inspect_err(|err| log::error!("Failed to start OSC server: {err}"))
+                .ok()
+        });
 
-            devices_to_init.push((device_name.to_string(), midi));
+        let inner = Arc::new(TriggersAgentInner {
+            midi_connections: Mutex::new(HashMap::new()),
+            midi_outputs: Mutex::new(HashMap::new()),
+            midi_learn_tx: Arc::new(Mutex::new(None)),
+        });
+
+        std::thread::Builder::new().name("rd_midi_supervisor".to_string()).spawn({
+            let inner = Arc::clone(&inner);
+            let mappings = Arc::clone(&midi_mappings);
+            let msc = Arc::clone(&msc);
+            let trigger_tx = trigger_tx.clone();
+            move || {
+                Self::supervise_midi(
+                    inner,
+                    mappings,
+                    msc,
+                    trigger_tx,
+                    midi_rescan_rx,
+                    midi_devices_tx,
+                )
+            }
+        })?;
+
+        log::info!("Started Triggers Agent");
+
+        Ok(Self {
+            definition,
+            trigger_tx,
+            trigger_rx,
+            midi_mappings,
+            msc,
+            midi_learn: None,
+            midi_devices: Arc::new(Vec::new()),
+            midi_devices_rx,
+            midi_rescan_tx,
+            osc_server,
+            inner,
+        })
+    }
+
+    pub fn drain(&self) -> Vec<Trigger> {
+        self.trigger_rx.try_iter().collect()
+    }
+
+    pub fn definition(&self) -> &TriggersDefinition {
+        &self.definition
+    }
+
+    /// Replaces the definition, reconnecting MIDI devices and restarting the
+    /// OSC server where needed.
+    pub fn set_definition(&mut self, definition: TriggersDefinition) -> anyhow::Result<()> {
+        *self
+            .midi_mappings
+            .write()
+            .map_err(|err| anyhow::anyhow!("Failed to lock MIDI mappings: {err}"))? =
+            definition.midi().to_vec();
+        *self
+            .msc
+            .write()
+            .map_err(|err| anyhow::anyhow!("Failed to lock MSC definition: {err}"))? =
+            definition.msc().cloned();
+
+        if self.definition.osc() != definition.osc() {
+            // Stop the old server first, as the new one will likely bind to the same port.
+            self.osc_server = None;
+            self.osc_server = definition.osc().and_then(|osc| {
+                OscServer::start(osc.clone(), self.trigger_tx.clone())
+                    .inspect_err(|err| log::error!("Failed to start OSC server: {err}"))
+                    .ok()
+            });
         }
 
-        let inner = Arc::new(TriggersAgentInner { _midi_connections: Mutex::new(Vec::new()) });
+        self.definition = definition;
+        self.request_midi_rescan();
+        Ok(())
+    }
 
-        let inner_clone = Arc::clone(&inner);
-        std::thread::spawn(move || {
-            log::info!("Initializing MIDI...");
+    pub fn midi_devices(&self) -> Arc<Vec<MidiDeviceStatus>> {
+        Arc::clone(&self.midi_devices)
+    }
 
-            let mut local_connections = Vec::new();
+    /// Picks up the latest MIDI device status from the supervisor. Returns
+    /// whether it changed.
+    pub fn poll_midi_devices(&mut self) -> bool {
+        match self.midi_devices_rx.try_iter().last() {
+            Some(devices) => {
+                self.midi_devices = Arc::new(devices);
+                true
+            }
+            None => false,
+        }
+    }
 
-            for (device_name, midi) in devices_to_init {
-                let midi_in = match midir::MidiInput::new("Radiant") {
-                    Ok(midi_in) => midi_in,
-                    Err(err) => {
-                        log::error!("Failed to create a MIDI input: {err}");
-                        continue;
-                    }
-                };
+    /// Sends feedback for the event. Executor feedback follows `selected_page`, like
+    /// the executor triggers do.
+    pub fn handle_event(&self, event: &Event, objects: &Objects, selected_page: Option<ObjectId>) {
+        if let Some(osc_server) = &self.osc_server {
+            osc_server.handle_event(event, objects, selected_page);
+        }
 
-                let port = match midi_in.ports().into_iter().find(|port| {
-                    midi_in.port_name(port).as_deref().ok() == Some(device_name.as_str())
-                }) {
-                    Some(port) => port,
-                    None => {
-                        let available_ports = midi_in
-                            .ports()
-                            .into_iter()
-                            .filter_map(|port| midi_in.port_name(&port).ok())
-                            .collect::<Vec<_>>();
-
-                        log::error!(
-                            "MIDI port not found: {}. available ports: {:?}",
-                            device_name,
-                            available_ports
-                        );
+        self.send_midi_feedback(event, objects, selected_page);
+        self.send_msc(event, objects);
+    }
 
-                        continue;
-                    }
+    /// Listens on all MIDI inputs and captures the next channel message as a
+    /// new trigger for `target`. See [TriggersAgent::poll_midi_learn].
+    pub fn start_midi_learn(&mut self, target: TriggerTarget) -> anyhow::Result<()> {
+        let has_inputs = midir::MidiInput::new("Radiant")
+            .map(|midi_in| midi_in.port_count() > 0)
+            .map_err(|err| anyhow::anyhow!("Failed to create a MIDI input: {err}"))?;
+        anyhow::ensure!(has_inputs, "No MIDI inputs available to learn from");
+
+        let (captured_tx, captured_rx) = flume::unbounded();
+        self.set_midi_learn_tx(Some(captured_tx))?;
+
+        log::info!("Started MIDI learn for {target:?}");
+        self.midi_learn = Some(MidiLearn { target, captured_rx });
+        Ok(())
+    }
+
+    pub fn cancel_midi_learn(&mut self) {
+        if self.midi_learn.take().is_some() {
+            self.set_midi_learn_tx(None).map_err(|err| log::error!("{err}")).ok();
+            log::info!("Cancelled MIDI learn");
+        }
+    }
+
+    pub fn is_midi_learning(&self) -> bool {
+        self.midi_learn.is_some()
+    }
+
+    /// Finishes MIDI learn if a message was captured, adding the resulting
+    /// definitions to the live agent and returning them.
+    pub fn poll_midi_learn(&mut self) -> anyhow::Result<Vec<MidiTriggerDefinition>> {
+        let Some(learn) = &self.midi_learn else { return Ok(Vec::new()) };
+        let Ok((device_name, bytes)) = learn.captured_rx.try_recv() else {
+            return Ok(Vec::new());
+        };
+
+        let target = learn.target.clone();
+        self.midi_learn = None;
+        self.set_midi_learn_tx(None)?;
+
+        let definitions = learned_midi_definitions(&device_name, &bytes, target)?;
+        for definition in &definitions {
+            self.add_midi_trigger(definition.clone())?;
+        }
+
+        log::info!("Learned MIDI trigger from '{device_name}': {bytes:?}");
+        Ok(definitions)
+    }
+
+    pub fn add_midi_trigger(&mut self, definition: MidiTriggerDefinition) -> anyhow::Result<()> {
+        self.definition.push_midi(definition.clone());
+        self.midi_mappings
+            .write()
+            .map_err(|err| anyhow::anyhow!("Failed to lock MIDI mappings: {err}"))?
+            .push(definition);
+        self.request_midi_rescan();
+        Ok(())
+    }
+
+    /// Makes the input connections forward what they receive to `learn_tx`. Devices
+    /// that aren't mapped are only connected while learning.
+    fn set_midi_learn_tx(
+        &self,
+        learn_tx: Option<flume::Sender<(String, Vec<u8>)>>,
+    ) -> anyhow::Result<()> {
+        *self
+            .inner
+            .midi_learn_tx
+            .lock()
+            .map_err(|err| anyhow::anyhow!("Failed to lock MIDI learn sender: {err}"))? = learn_tx;
+        self.request_midi_rescan();
+        Ok(())
+    }
+
+    fn request_midi_rescan(&self) {
+        let _ = self.midi_rescan_tx.send(());
+    }
+
+    /// Keeps the MIDI connections in line with the mapped devices, picking up
+    /// devices that are plugged in later and dropping ones that disappear.
+    fn supervise_midi(
+        inner: Arc<TriggersAgentInner>,
+        mappings: Arc<RwLock<Vec<MidiTriggerDefinition>>>,
+        msc: Arc<RwLock<Option<MscDefinition>>>,
+        trigger_tx: flume::Sender<Trigger>,
+        rescan_rx: flume::Receiver<()>,
+        devices_tx: flume::Sender<Vec<MidiDeviceStatus>>,
+    ) {
+        log::info!("Initializing MIDI...");
+
+        let mut last_devices = None;
+        loop {
+            let devices = Self::rescan_midi(&inner, &mappings, &msc, &trigger_tx);
+            if last_devices.as_ref() != Some(&devices) {
+                log::info!("MIDI devices: {devices:?}");
+                let _ = devices_tx.send(devices.clone());
+                last_devices = Some(devices);
+            }
+
+            match rescan_rx.recv_timeout(MIDI_RESCAN_INTERVAL) {
+                Ok(()) | Err(flume::RecvTimeoutError::Timeout) => {}
+                Err(flume::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        log::debug!("Stopped MIDI supervisor");
+    }
+
+    fn rescan_midi(
+        inner: &TriggersAgentInner,
+        mappings: &Arc<RwLock<Vec<MidiTriggerDefinition>>>,
+        msc: &Arc<RwLock<Option<MscDefinition>>>,
+        trigger_tx: &flume::Sender<Trigger>,
+    ) -> Vec<MidiDeviceStatus> {
+        let device_names: BTreeSet<String> = match (mappings.read(), msc.read()) {
+            (Ok(mappings), Ok(msc)) => mappings
+                .iter()
+                .map(|m| m.device_name())
+                .chain(msc.as_ref().map(|msc| msc.device_name()))
+                .map(str::to_string)
+                .collect(),
+            _ => {
+                log::error!("Failed to lock MIDI mappings");
+                return Vec::new();
+            }
+        };
+
+        let (Ok(mut connections), Ok(mut outputs), Ok(learn_tx)) =
+            (inner.midi_connections.lock(), inner.midi_outputs.lock(), inner.midi_learn_tx.lock())
+        else {
+            log::error!("Failed to lock MIDI connections guard");
+            return Vec::new();
+        };
+        let learning = learn_tx.is_some();
+        drop(learn_tx);
+
+        if device_names.is_empty() && !learning {
+            connections.clear();
+            outputs.clear();
+            return Vec::new();
+        }
+
+        let input_ports = midir::MidiInput::new("Radiant")
+            .map(|midi_in| {
+                midi_in.ports().iter().filter_map(|port| midi_in.port_name(port).ok()).collect()
+            })
+            .unwrap_or_else(|err| {
+                log::error!("Failed to create a MIDI input: {err}");
+                BTreeSet::new()
+            });
+        let output_ports = midir::MidiOutput::new("Radiant")
+            .map(|midi_out| {
+                midi_out.ports().iter().filter_map(|port| midi_out.port_name(port).ok()).collect()
+            })
+            .unwrap_or_else(|err| {
+                log::error!("Failed to create a MIDI output: {err}");
+                BTreeSet::new()
+            });
+
+        // While learning, every input is connected so any device can be learned from.
+        let input_names = if learning { &input_ports } else { &device_names };
+        connections.retain(|name, _| input_names.contains(name) && input_ports.contains(name));
+        outputs.retain(|name, _| device_names.contains(name) && output_ports.contains(name));
+
+        for device_name in input_names {
+            if !connections.contains_key(device_name) && input_ports.contains(device_name) {
+                let context = MidiInputContext {
+                    device_name: device_name.clone(),
+                    decoder: MidiDecoder::default(),
+                    mappings: Arc::clone(mappings),
+                    msc: Arc::clone(msc),
+                    trigger_tx: trigger_tx.clone(),
+                    learn_tx: Arc::clone(&inner.midi_learn_tx),
                 };
+                match connect_midi_input(device_name, Self::handle_midi_event, context) {
+                    Ok(connection) => {
+                        log::info!("Connected MIDI input '{device_name}'");
+                        connections.insert(device_name.clone(), connection);
+                    }
+                    Err(err) => log::error!("{err}"),
+                }
+            }
+        }
 
-                let midi_connection = match midi_in.connect(
-                    &port,
-                    "Radiant",
-                    Self::handle_midi_event,
-                    (midi, trigger_tx.clone()),
-                ) {
-                    Ok(midi_connection) => midi_connection,
+        for device_name in &device_names {
+            if !outputs.contains_key(device_name) && output_ports.contains(device_name) {
+                match Self::connect_midi_output(device_name) {
+                    Ok(Some(connection)) => {
+                        log::info!("Connected MIDI output '{device_name}'");
+                        outputs.insert(device_name.clone(), connection);
+                    }
+                    Ok(None) => {}
                     Err(err) => {
-                        log::error!("Failed to connect to MIDI port: {err}");
-                        continue;
+                        log::error!("Failed to connect to MIDI output '{device_name}': {err}")
                     }
-                };
+                }
+            }
+        }
+
+        device_names
+            .into_iter()
+            .map(|device_name| MidiDeviceStatus {
+                input_connected: connections.contains_key(&device_name),
+                output_connected: outputs.contains_key(&device_name),
+                device_name,
+            })
+            .collect()
+    }
+
+    fn connect_midi_output(device_name: &str) -> anyhow::Result<Option<MidiOutputConnection>> {
+        let midi_out = midir::MidiOutput::new("Radiant")?;
+        let Some(port) = midi_out
+            .ports()
+            .into_iter()
+            .find(|port| midi_out.port_name(port).as_deref().ok() == Some(device_name))
+        else {
+            return Ok(None);
+        };
+        let connection =
+            midi_out.connect(&port, "Radiant").map_err(|err| anyhow::anyhow!("{err}"))?;
+        Ok(Some(connection))
+    }
+
+    /// Sends the current state of mapped executors and encoders back to the
+    /// devices, so button LEDs, motor faders and encoder rings follow the console.
+    fn send_midi_feedback(
+        &self,
+        event: &Event,
+        objects: &Objects,
+        selected_page: Option<ObjectId>,
+    ) {
+        let messages = self.midi_feedback_messages(event, objects, selected_page);
+        if messages.is_empty() {
+            return;
+        }
 
-                local_connections.push(midi_connection);
+        let mut outputs = match self.inner.midi_outputs.lock() {
+            Ok(outputs) => outputs,
+            Err(err) => {
+                log::error!("Failed to lock MIDI outputs guard: {err}");
+                return;
             }
+        };
 
-            match inner_clone._midi_connections.lock() {
-                Ok(mut guard) => *guard = local_connections,
-                Err(err) => log::error!("Failed to lock MIDI connections guard: {err}"),
+        for (device_name, bytes) in messages {
+            let Some(connection) = outputs.get_mut(device_name) else { continue };
+            if let Err(err) = connection.send(&bytes) {
+                log::warn!("Failed to send MIDI feedback to '{device_name}': {err}");
             }
+        }
+    }
 
-            log::info!("MIDI Initialized");
-        });
+    /// The feedback messages for an event, per device.
+    pub(crate) fn midi_feedback_messages(
+        &self,
+        event: &Event,
+        objects: &Objects,
+        selected_page: Option<ObjectId>,
+    ) -> Vec<(&str, Vec<u8>)> {
+        let mut messages = Vec::new();
+        for mapping in self.definition.midi() {
+            let value = match mapping.target() {
+                TriggerTarget::ExecutorMaster { executor_id } => {
+                    let executor_id = executor_id.on_selected_page(selected_page);
+                    if !changes_executor(event, &executor_id) {
+                        continue;
+                    }
+                    let Ok(executor) = objects.executor(&executor_id) else { continue };
+                    executor.master()
+                }
+                TriggerTarget::ExecutorButton { executor_id, .. } => {
+                    let executor_id = executor_id.on_selected_page(selected_page);
+                    if !changes_executor(event, &executor_id) {
+                        continue;
+                    }
+                    let Ok(executor) = objects.executor(&executor_id) else { continue };
+                    if executor.enabled() { 1.0 } else { 0.0 }
+                }
+                TriggerTarget::Encoder { encoder_ix } => match event {
+                    Event::EncoderChanged { encoder_ix: changed_ix, value }
+                        if changed_ix == encoder_ix =>
+                    {
+                        *value
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            let Some(bytes) = midi::feedback_messages(mapping, value) else { continue };
+            for bytes in bytes {
+                let message = (mapping.device_name(), bytes);
+                // Multiple mappings (e.g. NoteOn and NoteOff) often share the same control.
+                if !messages.contains(&message) {
+                    messages.push(message);
+                }
+            }
+        }
+        messages
+    }
 
-        log::info!("Started Triggers Agent");
+    /// Sends locally fired cues as MSC GO messages, so other departments can follow.
+    fn send_msc(&self, event: &Event, objects: &Objects) {
+        let Some(msc) = self.definition.msc() else { return };
+        let Some(bytes) = self.msc_go_message(event, objects) else { return };
 
-        Ok(Self { definition, trigger_rx, _inner: inner })
-    }
+        let mut outputs = match self.inner.midi_outputs.lock() {
+            Ok(outputs) => outputs,
+            Err(err) => {
+                log::error!("Failed to lock MIDI outputs guard: {err}");
+                return;
+            }
+        };
 
-    pub fn drain(&self) -> Vec<Trigger> {
-        self.trigger_rx.try_iter().collect()
+        let Some(connection) = outputs.get_mut(msc.device_name()) else { return };
+        if let Err(err) = connection.send(&bytes) {
+            log::warn!("Failed to send MSC to '{}': {err}", msc.device_name());
+        }
     }
 
-    pub fn definition(&self) -> &TriggersDefinition {
-        &self.definition
+    /// The MSC GO to transmit for an event. Cues fired by incoming MSC are not echoed,
+    /// as two consoles following each other would loop.
+    pub(crate) fn msc_go_message(&self, event: &Event, objects: &Objects) -> Option<Vec<u8>> {
+        let Event::CueFired { executor_id, from_msc: false, .. } = event else {
+            return None;
+        };
+        let device_id = self.definition.msc()?.transmit_device_id()?;
+        let cue = msc::executor_cue(executor_id, objects)?;
+        Some(MscMessage::lighting(device_id.as_u8(), MscCommand::Go { cue: Some(cue) }).encode())
     }
 
-    fn handle_midi_event(
-        _timestamp: u64,
-        event_bytes: &[u8],
-        (midi_mappings, trigger_tx): &mut (Vec<MidiTriggerDefinition>, flume::Sender<Trigger>),
-    ) {
+    fn handle_midi_event(_timestamp: u64, event_bytes: &[u8], context: &mut MidiInputContext) {
         let event = match midly::live::LiveEvent::parse(event_bytes) {
             Err(err) => {
                 log::warn!("Received invalid MIDI bytes {:?}: {}", event_bytes, err);
@@ -130,85 +527,98 @@ impl TriggersAgent {
 
         log::debug!("Received MIDI event: {:?}", event);
 
+        if let midly::live::LiveEvent::Common(midly::live::SystemCommon::SysEx(_)) = event {
+            Self::handle_msc_event(event_bytes, context);
+            return;
+        }
+
         let midly::live::LiveEvent::Midi { channel, message } = event else { return };
 
-        let triggers = midi_mappings
-            .into_iter()
-            .filter(|mapping| {
-                let channels_match = mapping.channel().contains(&channel.into());
-                let messages_match = match (mapping.trigger(), message) {
-                    (
-                        MidiMessage::NoteOff { note, velocity },
-                        midly::MidiMessage::NoteOff { key, vel },
-                    ) => note.contains(&key.into()) && velocity.contains(&vel.into()),
-                    (
-                        MidiMessage::NoteOn { note, velocity },
-                        midly::MidiMessage::NoteOn { key, vel },
-                    ) => note.contains(&key.into()) && velocity.contains(&vel.into()),
-                    (
-                        MidiMessage::PolyphonicAftertouch { note, pressure },
-                        midly::MidiMessage::Aftertouch { key, vel },
-                    ) => note.contains(&key.into()) && pressure.contains(&vel.into()),
-                    (
-                        MidiMessage::ControlChange { controller: ctrl1, value: val1 },
-                        midly::MidiMessage::Controller { controller: ctrl2, value: val2 },
-                    ) => ctrl1.contains(&ctrl2.into()) && val1.contains(&val2.into()),
-                    (
-                        MidiMessage::ProgramChange { program: prog1 },
-                        midly::MidiMessage::ProgramChange { program: prog2 },
-                    ) => prog1.contains(&prog2.into()),
-                    (
-                        MidiMessage::ChannelAftertouch { pressure },
-                        midly::MidiMessage::ChannelAftertouch { vel },
-                    ) => pressure.contains(&vel.into()),
-                    (MidiMessage::PitchBend { value }, midly::MidiMessage::PitchBend { bend }) => {
-                        value.contains(&bend.as_int())
-                    }
-                    _ => false,
-                };
+        // Only channel messages can be mapped, so clock, sysex and the like aren't learned.
+        if let Ok(learn_tx) = context.learn_tx.lock()
+            && let Some(learn_tx) = learn_tx.as_ref()
+        {
+            let _ = learn_tx.send((context.device_name.clone(), event_bytes.to_vec()));
+        }
 
-                let matched = channels_match && messages_match;
-                matched
-            })
+        let channel = channel.into();
+        context.decoder.update(channel, &message);
+
+        let midi_mappings = match context.mappings.read() {
+            Ok(midi_mappings) => midi_mappings,
+            Err(err) => {
+                log::error!("Failed to lock MIDI mappings: {err}");
+                return;
+            }
+        };
+
+        let triggers = midi_mappings
+            .iter()
+            .filter(|mapping| mapping.device_name() == context.device_name)
             .filter_map(|mapping| {
-                let target = mapping.target();
-                match target {
-                    TriggerTarget::ExecutorMaster { executor_id } => {
-                        let Some(value) = midi_value_as_f32(&message) else {
-                            log::warn!(
-                                "Mapped MIDI message did not carry a value usable as f32: {:?}",
-                                message
-                            );
-                            return None;
-                        };
-                        Some(Trigger::ExecutorMaster { executor_id: *executor_id, value })
-                    }
-                    TriggerTarget::ExecutorButton { executor_id, button } => {
-                        let pressed = midi_pressed(&message);
-                        Some(Trigger::ExecutorButton {
-                            executor_id: *executor_id,
-                            button: *button,
-                            pressed,
-                        })
-                    }
-                    TriggerTarget::Encoder { encoder_ix } => {
-                        let Some(value) = midi_value_as_f32(&message) else {
-                            log::warn!(
-                                "Mapped MIDI message did not contain a value usable as f32: {:?}",
-                                message
-                            );
-                            return None;
-                        };
-                        Some(Trigger::EncoderSetValue { encoder_ix: *encoder_ix, value })
-                    }
-                }
+                let decoded = context.decoder.decode(mapping, channel, &message)?;
+                let (value, relative) = match decoded.value {
+                    MidiValue::Absolute(value) => (value, false),
+                    MidiValue::Relative(delta) => (delta, true),
+                };
+                mapping.target().to_trigger(value, relative, decoded.pressed)
             })
             .collect::<Vec<_>>();
 
         for trigger in triggers {
             log::debug!("Sending trigger: {:?}", event);
 
-            if let Err(err) = trigger_tx.send(trigger) {
+            if let Err(err) = context.trigger_tx.send(trigger) {
+                log::error!("Failed to send trigger: {}", err);
+            }
+        }
+    }
+
+    fn handle_msc_event(event_bytes: &[u8], context: &mut MidiInputContext) {
+        let msc = match context.msc.read() {
+            Ok(msc) => msc,
+            Err(err) => {
+                log::error!("Failed to lock MSC definition: {err}");
+                return;
+            }
+        };
+        let Some(msc) = msc.as_ref().filter(|msc| msc.device_name() == context.device_name) else {
+            return;
+        };
+
+        let message = match MscMessage::decode(event_bytes) {
+            Ok(message) => message,
+            Err(msc::MscError::NotMsc) => return,
+            Err(err) => {
+                log::warn!("Received invalid MSC message {:?}: {}", event_bytes, err);
+                return;
+            }
+        };
+
+        if !message.is_addressed_to(msc.device_id().as_u8()) {
+            return;
+        }
+
+        log::debug!("Received MSC message: {:?}", message);
+
+        let triggers = match message.command {
+            MscCommand::Fire { macro_number } => msc
+                .macros()
+                .iter()
+                .filter(|m| m.number().as_u8() == macro_number)
+                .flat_map(|m| {
+                    [
+                        m.target().to_trigger(1.0, false, true),
+                        m.target().to_trigger(0.0, false, false),
+                    ]
+                })
+                .flatten()
+                .collect(),
+            command => vec![Trigger::Msc(command)],
+        };
+
+        for trigger in triggers {
+            if let Err(err) = context.trigger_tx.send(trigger) {
                 log::error!("Failed to send trigger: {}", err);
             }
         }
@@ -217,40 +627,153 @@ impl TriggersAgent {
 
 impl Default for TriggersAgent {
     fn default() -> Self {
-        let (_, trigger_rx) = flume::bounded(1);
+        let (trigger_tx, trigger_rx) = flume::bounded(1);
+        let (midi_rescan_tx, _) = flume::unbounded();
+        let (_, midi_devices_rx) = flume::unbounded();
         Self {
             definition: TriggersDefinition::default(),
+            trigger_tx,
             trigger_rx,
-            _inner: Arc::new(TriggersAgentInner { _midi_connections: Default::default() }),
+            midi_mappings: Default::default(),
+            msc: Default::default(),
+            midi_learn: None,
+            midi_devices: Default::default(),
+            midi_devices_rx,
+            midi_rescan_tx,
+            osc_server: None,
+            inner: Arc::new(TriggersAgentInner {
+                midi_connections: Default::default(),
+                midi_outputs: Default::default(),
+                midi_learn_tx: Default::default(),
+            }),
         }
     }
 }
 
-fn midi_pressed(message: &midly::MidiMessage) -> bool {
-    match *message {
-        midly::MidiMessage::NoteOn { .. } => true,
-        midly::MidiMessage::NoteOff { .. } => false,
-        midly::MidiMessage::Controller { value, .. }
-        | midly::MidiMessage::Aftertouch { vel: value, .. }
-        | midly::MidiMessage::ChannelAftertouch { vel: value } => value.as_int() > 0,
-        midly::MidiMessage::ProgramChange { .. } => true,
-        midly::MidiMessage::PitchBend { .. } => true,
-    }
+fn connect_midi_input<T: Send + 'static>(
+    device_name: &str,
+    callback: fn(u64, &[u8], &mut T),
+    data: T,
+) -> anyhow::Result<MidiInputConnection<T>> {
+    let midi_in = midir::MidiInput::new("Radiant")
+        .map_err(|err| anyhow::anyhow!("Failed to create a MIDI input: {err}"))?;
+
+    let Some(port) = midi_in
+        .ports()
+        .into_iter()
+        .find(|port| midi_in.port_name(port).as_deref().ok() == Some(device_name))
+    else {
+        let available_ports = midi_in
+            .ports()
+            .into_iter()
+            .filter_map(|port| midi_in.port_name(&port).ok())
+            .collect::<Vec<_>>();
+        anyhow::bail!("MIDI port not found: {device_name}. available ports: {available_ports:?}");
+    };
+
+    midi_in
+        .connect(&port, "Radiant", callback, data)
+        .map_err(|err| anyhow::anyhow!("Failed to connect to MIDI port: {err}"))
 }
 
-fn midi_value_as_f32(message: &midly::MidiMessage) -> Option<f32> {
-    let v = match *message {
-        midly::MidiMessage::NoteOff { vel, .. }
-        | midly::MidiMessage::NoteOn { vel, .. }
-        | midly::MidiMessage::Aftertouch { vel, .. }
-        | midly::MidiMessage::ChannelAftertouch { vel }
-        | midly::MidiMessage::Controller { value: vel, .. } => vel.as_int() as f32 / 127.0,
-        midly::MidiMessage::ProgramChange { program } => program.as_int() as f32 / 127.0,
-        midly::MidiMessage::PitchBend { bend } => {
-            let v = bend.as_int() as f32;
-            (v + 8192.0) / 16383.0
+/// Turns a captured MIDI message into trigger definitions that match the
+/// same control over its full value range.
+fn learned_midi_definitions(
+    device_name: &str,
+    event_bytes: &[u8],
+    target: TriggerTarget,
+) -> anyhow::Result<Vec<MidiTriggerDefinition>> {
+    let event = midly::live::LiveEvent::parse(event_bytes)
+        .map_err(|err| anyhow::anyhow!("Invalid MIDI bytes {event_bytes:?}: {err}"))?;
+    let midly::live::LiveEvent::Midi { channel, message } = event else {
+        anyhow::bail!("Learned MIDI event is not a channel message: {event_bytes:?}");
+    };
+
+    let channel = MultiRange::Single(channel.into());
+    let full_range = || MultiRange::Range { from: u7::MIN, to: u7::MAX };
+    let message = match message {
+        midly::MidiMessage::NoteOff { key, .. } => {
+            MidiMessage::NoteOff { note: MultiRange::Single(key.into()), velocity: full_range() }
+        }
+        midly::MidiMessage::NoteOn { key, .. } => {
+            MidiMessage::NoteOn { note: MultiRange::Single(key.into()), velocity: full_range() }
+        }
+        midly::MidiMessage::Aftertouch { key, .. } => MidiMessage::PolyphonicAftertouch {
+            note: MultiRange::Single(key.into()),
+            pressure: full_range(),
+        },
+        midly::MidiMessage::Controller { controller, .. } => MidiMessage::ControlChange {
+            controller: MultiRange::Single(controller.into()),
+            value: full_range(),
+        },
+        midly::MidiMessage::ProgramChange { program } => {
+            MidiMessage::ProgramChange { program: MultiRange::Single(program.into()) }
+        }
+        midly::MidiMessage::ChannelAftertouch { .. } => {
+            MidiMessage::ChannelAftertouch { pressure: full_range() }
+        }
+        midly::MidiMessage::PitchBend { .. } => {
+            MidiMessage::PitchBend { value: MultiRange::Range { from: -8192, to: 8191 } }
         }
     };
 
-    Some(v.clamp(0.0, 1.0))
+    let mut definitions = Vec::new();
+
+    // Most devices release a note button with a NoteOff, so learn that as well.
+    if let (MidiMessage::NoteOn { note, .. }, TriggerTarget::ExecutorButton { .. }) =
+        (&message, &target)
+    {
+        let note_off = MidiMessage::NoteOff { note: note.clone(), velocity: full_range() };
+        definitions.push(MidiTriggerDefinition::new(
+            device_name.to_string(),
+            channel.clone(),
+            note_off,
+            target.clone(),
+        ));
+    }
+
+    definitions
+        .insert(0, MidiTriggerDefinition::new(device_name.to_string(), channel, message, target));
+
+    Ok(definitions)
+}
+
+/// Whether the executor changed, or came into view because its page was selected.
+fn changes_executor(event: &Event, executor_id: &ExecutorId) -> bool {
+    match event {
+        Event::ObjectChanged { object_kind: ObjectKind::ExecutorPage, object_id }
+        | Event::ExecutorPageSelected { object_id } => executor_id.page == *object_id,
+        Event::CueFired { executor_id: fired, .. } => executor_id == fired,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn learn_note_button() {
+        let target: TriggerTarget = serde_json::from_value(json!({
+            "type": "ExecutorButton",
+            "executor_id": { "page": "7c9e6679-7425-40de-944b-e07fc1f90ae7", "slot": 2 },
+            "button": "Button1",
+        }))
+        .unwrap();
+
+        let definitions = learned_midi_definitions("APC", &[0x91, 53, 127], target).unwrap();
+        let definitions = serde_json::to_value(definitions).unwrap();
+        assert_eq!(definitions[0]["channel"], 1);
+        assert_eq!(definitions[0]["message"]["type"], "NoteOn");
+        assert_eq!(definitions[0]["message"]["note"], 53);
+        assert_eq!(definitions[0]["message"]["velocity"], json!({ "from": 0, "to": 127 }));
+        assert_eq!(definitions[1]["message"]["type"], "NoteOff");
+
+        assert!(
+            learned_midi_definitions("APC", &[0xF8], TriggerTarget::Encoder { encoder_ix: 0 })
+                .is_err()
+        );
+    }
 }
@@ -1,15 +1,173 @@
-use crate::object::{ExecutorButton, ExecutorId};
+use std::net::SocketAddr;
+
+use crate::{
+    cmd::Command,
+    gdtf::attr::AttributeName,
+    object::{ExecutorButton, ExecutorId, ObjectId, ObjectKind},
+    trigger::Trigger,
+    value::ClampedValue,
+};
 
 #[derive(Debug, Clone, PartialEq, Default)]
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct TriggersDefinition {
     midi: Vec<MidiTriggerDefinition>,
+    #[serde(default)]
+    osc: Option<OscDefinition>,
+    #[serde(default)]
+    msc: Option<MscDefinition>,
 }
 
 impl TriggersDefinition {
     pub fn midi(&self) -> &[MidiTriggerDefinition] {
         &self.midi
     }
+
+    pub fn osc(&self) -> Option<&OscDefinition> {
+        self.osc.as_ref()
+    }
+
+    pub fn msc(&self) -> Option<&MscDefinition> {
+        self.msc.as_ref()
+    }
+
+    pub fn push_midi(&mut self, definition: MidiTriggerDefinition) {
+        self.midi.push(definition);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OscDefinition {
+    listen_address: SocketAddr,
+    #[serde(default)]
+    feedback_addresses: Vec<SocketAddr>,
+    #[serde(default)]
+    triggers: Vec<OscTriggerDefinition>,
+    #[serde(default)]
+    feedback: Vec<OscFeedbackDefinition>,
+}
+
+impl OscDefinition {
+    pub fn listen_address(&self) -> SocketAddr {
+        self.listen_address
+    }
+
+    pub fn feedback_addresses(&self) -> &[SocketAddr] {
+        &self.feedback_addresses
+    }
+
+    pub fn triggers(&self) -> &[OscTriggerDefinition] {
+        &self.triggers
+    }
+
+    pub fn feedback(&self) -> &[OscFeedbackDefinition] {
+        &self.feedback
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OscTriggerDefinition {
+    /// An OSC address pattern (e.g. `/exec/{1,2}/fader`) matched against incoming addresses.
+    address: String,
+    target: TriggerTarget,
+}
+
+impl OscTriggerDefinition {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn target(&self) -> &TriggerTarget {
+        &self.target
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct OscFeedbackDefinition {
+    address: String,
+    source: OscFeedbackSource,
+}
+
+impl OscFeedbackDefinition {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn source(&self) -> &OscFeedbackSource {
+        &self.source
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum OscFeedbackSource {
+    ExecutorMaster { executor_id: ExecutorId },
+    ExecutorEnabled { executor_id: ExecutorId },
+    ExecutorCueName { executor_id: ExecutorId },
+}
+
+impl OscFeedbackSource {
+    pub fn executor_id(&self) -> ExecutorId {
+        match self {
+            OscFeedbackSource::ExecutorMaster { executor_id }
+            | OscFeedbackSource::ExecutorEnabled { executor_id }
+            | OscFeedbackSource::ExecutorCueName { executor_id } => *executor_id,
+        }
+    }
+}
+
+/// MIDI Show Control on a MIDI device, driving sequence playback.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MscDefinition {
+    device_name: String,
+    /// The device ID this console responds to, in addition to the all-call ID.
+    device_id: u7,
+    /// When set, locally fired cues are sent as GO messages to this device ID.
+    #[serde(default)]
+    transmit_device_id: Option<u7>,
+    #[serde(default)]
+    macros: Vec<MscMacroDefinition>,
+}
+
+impl MscDefinition {
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    pub fn device_id(&self) -> u7 {
+        self.device_id
+    }
+
+    pub fn transmit_device_id(&self) -> Option<u7> {
+        self.transmit_device_id
+    }
+
+    pub fn macros(&self) -> &[MscMacroDefinition] {
+        &self.macros
+    }
+}
+
+/// Maps an MSC FIRE macro number onto a [TriggerTarget].
+#[derive(Debug, Clone, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MscMacroDefinition {
+    number: u7,
+    target: TriggerTarget,
+}
+
+impl MscMacroDefinition {
+    pub fn number(&self) -> u7 {
+        self.number
+    }
+
+    pub fn target(&self) -> &TriggerTarget {
+        &self.target
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,10 +176,30 @@ pub struct MidiTriggerDefinition {
     device_name: String,
     channel: MultiRange<u4>,
     message: MidiMessage,
+    #[serde(default)]
+    encoding: MidiValueEncoding,
+    #[serde(default)]
+    feedback: MidiFeedbackMode,
     target: TriggerTarget,
 }
 
 impl MidiTriggerDefinition {
+    pub fn new(
+        device_name: String,
+        channel: MultiRange<u4>,
+        message: MidiMessage,
+        target: TriggerTarget,
+    ) -> Self {
+        Self {
+            device_name,
+            channel,
+            message,
+            encoding: MidiValueEncoding::default(),
+            feedback: MidiFeedbackMode::default(),
+            target,
+        }
+    }
+
     pub fn device_name(&self) -> &str {
         &self.device_name
     }
@@ -34,6 +212,14 @@ impl MidiTriggerDefinition {
         &self.message
     }
 
+    pub fn encoding(&self) -> &MidiValueEncoding {
+        &self.encoding
+    }
+
+    pub fn feedback(&self) -> MidiFeedbackMode {
+        self.feedback
+    }
+
     pub fn target(&self) -> &TriggerTarget {
         &self.target
     }
@@ -43,22 +229,167 @@ impl MidiTriggerDefinition {
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
 pub enum MidiMessage {
-    NoteOff { note: MultiRange<u7>, velocity: MultiRange<u7> },
-    NoteOn { note: MultiRange<u7>, velocity: MultiRange<u7> },
-    PolyphonicAftertouch { note: MultiRange<u7>, pressure: MultiRange<u7> },
-    ControlChange { controller: MultiRange<u7>, value: MultiRange<u7> },
-    ProgramChange { program: MultiRange<u7> },
-    ChannelAftertouch { pressure: MultiRange<u7> },
-    PitchBend { value: MultiRange<i16> },
+    NoteOff {
+        note: MultiRange<u7>,
+        velocity: MultiRange<u7>,
+    },
+    NoteOn {
+        note: MultiRange<u7>,
+        velocity: MultiRange<u7>,
+    },
+    PolyphonicAftertouch {
+        note: MultiRange<u7>,
+        pressure: MultiRange<u7>,
+    },
+    ControlChange {
+        controller: MultiRange<u7>,
+        value: MultiRange<u7>,
+    },
+    ProgramChange {
+        program: MultiRange<u7>,
+    },
+    ChannelAftertouch {
+        pressure: MultiRange<u7>,
+    },
+    PitchBend {
+        value: MultiRange<i16>,
+    },
+    /// A non-registered parameter, sent as a sequence of control changes.
+    Nrpn {
+        parameter: MultiRange<u16>,
+    },
+}
+
+/// How the value of a [MidiMessage] is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum MidiValueEncoding {
+    /// The 7-bit value (or 14-bit for pitch bend and NRPN) is the position.
+    #[default]
+    Absolute,
+    /// A 14-bit position from a control change pair. The message controller is
+    /// the MSB, this controller the LSB (usually MSB + 32).
+    Absolute14Bit { lsb_controller: u7 },
+    /// Relative ticks where 1..=63 is up and 127..=65 is down by 1..=63.
+    RelativeTwosComplement,
+    /// Relative ticks where bit 6 is the sign and bits 0..=5 the magnitude.
+    RelativeSignMagnitude,
+    /// Relative ticks as an offset from 64.
+    RelativeBinaryOffset,
+}
+
+impl MidiValueEncoding {
+    pub fn is_relative(&self) -> bool {
+        matches!(
+            self,
+            Self::RelativeTwosComplement | Self::RelativeSignMagnitude | Self::RelativeBinaryOffset
+        )
+    }
+}
+
+/// What is sent back to the control of a mapping when its target changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum MidiFeedbackMode {
+    /// The new position in the encoding of the mapping. Relative encodings have no
+    /// position, so nothing is sent for them.
+    #[default]
+    Encoding,
+    /// The new position as an absolute value, even for relative encodings. Most
+    /// encoders with an LED ring take this to draw the ring.
+    Absolute,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
 pub enum TriggerTarget {
-    ExecutorMaster { executor_id: ExecutorId },
-    ExecutorButton { executor_id: ExecutorId, button: ExecutorButton },
-    Encoder { encoder_ix: usize },
+    ExecutorMaster {
+        executor_id: ExecutorId,
+    },
+    ExecutorButton {
+        executor_id: ExecutorId,
+        button: ExecutorButton,
+    },
+    Encoder {
+        encoder_ix: usize,
+    },
+    /// Sets the attribute for the current selection, scaling the incoming value from `from` to `to`.
+    ProgrammerAttribute {
+        attribute: AttributeName,
+        from: ClampedValue,
+        to: ClampedValue,
+    },
+    Command {
+        command: Box<Command>,
+    },
+    /// Selects the page that executor triggers, executor feedback and MSC address. The
+    /// executors in the trigger definitions then stand for the same slot on that page.
+    ExecutorPageSelect {
+        object_id: ObjectId,
+    },
+    Activate {
+        object_kind: ObjectKind,
+        object_id: ObjectId,
+    },
+    ProgrammerClear,
+    HighlightToggle,
+}
+
+impl TriggerTarget {
+    /// Whether the target follows a continuous value, as opposed to firing once when pressed.
+    pub fn takes_value(&self) -> bool {
+        matches!(
+            self,
+            TriggerTarget::ExecutorMaster { .. }
+                | TriggerTarget::Encoder { .. }
+                | TriggerTarget::ProgrammerAttribute { .. }
+        )
+    }
+
+    pub fn to_trigger(&self, value: f32, relative: bool, pressed: bool) -> Option<Trigger> {
+        let command = match self {
+            TriggerTarget::ExecutorMaster { executor_id } => {
+                return Some(Trigger::ExecutorMaster {
+                    executor_id: *executor_id,
+                    value,
+                    relative,
+                });
+            }
+            TriggerTarget::ExecutorButton { executor_id, button } => {
+                return Some(Trigger::ExecutorButton {
+                    executor_id: *executor_id,
+                    button: *button,
+                    pressed,
+                });
+            }
+            TriggerTarget::Encoder { encoder_ix } => {
+                return Some(Trigger::EncoderSetValue { encoder_ix: *encoder_ix, value, relative });
+            }
+            TriggerTarget::ProgrammerAttribute { attribute, from, to } => {
+                return Some(Trigger::ProgrammerAttribute {
+                    attribute: attribute.clone(),
+                    from: *from,
+                    to: *to,
+                    value,
+                    relative,
+                });
+            }
+            TriggerTarget::Command { command } => command.as_ref().clone(),
+            TriggerTarget::ExecutorPageSelect { object_id } => {
+                Command::ExecutorPageSelect { object_id: *object_id }
+            }
+            TriggerTarget::Activate { object_kind, object_id } => {
+                Command::Activate { object_kind: *object_kind, object_id: *object_id }
+            }
+            TriggerTarget::ProgrammerClear => Command::ProgrammerClear,
+            TriggerTarget::HighlightToggle => Command::HighlightToggle,
+        };
+
+        pressed.then_some(Trigger::Command(command))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -78,6 +409,15 @@ impl<T: PartialOrd + Copy> MultiRange<T> {
             MultiRange::Selection(ranges) => ranges.iter().any(|r| r.contains(value)),
         }
     }
+
+    /// The lowest value in the range, used when a single value has to be sent back.
+    pub fn first(&self) -> Option<T> {
+        match self {
+            MultiRange::Single(v) => Some(*v),
+            MultiRange::Range { from, .. } => Some(*from),
+            MultiRange::Selection(ranges) => ranges.iter().find_map(MultiRange::first),
+        }
+    }
 }
 
 #[allow(non_camel_case_types)]
@@ -85,6 +425,9 @@ impl<T: PartialOrd + Copy> MultiRange<T> {
 pub struct u7(u8);
 
 impl u7 {
+    pub const MIN: u7 = u7(0);
+    pub const MAX: u7 = u7(127);
+
     pub fn new(value: u8) -> anyhow::Result<Self> {
         if value <= 127 {
             Ok(u7(value))
@@ -165,3 +508,26 @@ impl serde::Serialize for u4 {
         serializer.serialize_u8(self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn command_target_fires_on_press() {
+        let target: TriggerTarget = serde_json::from_value(json!({
+            "type": "Command",
+            "command": { "type": "Highlight", "enabled": true },
+        }))
+        .unwrap();
+
+        assert!(!target.takes_value());
+        assert!(matches!(
+            target.to_trigger(1.0, false, true),
+            Some(Trigger::Command(Command::Highlight { enabled: true }))
+        ));
+        assert!(target.to_trigger(0.0, false, false).is_none());
+    }
+}
@@ -0,0 +1,447 @@
+//! Decoding of incoming MIDI into trigger values, and encoding of feedback.
+
+use std::collections::HashMap;
+
+use crate::trigger::{MidiFeedbackMode, MidiMessage, MidiTriggerDefinition, MidiValueEncoding, u4};
+
+/// How far a single tick of a relative encoder moves a value.
+pub const RELATIVE_STEP: f32 = 1.0 / 127.0;
+
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+const DATA_INCREMENT: u8 = 96;
+const DATA_DECREMENT: u8 = 97;
+const NRPN_PARAMETER_LSB: u8 = 98;
+const NRPN_PARAMETER_MSB: u8 = 99;
+const RPN_PARAMETER_LSB: u8 = 100;
+const RPN_PARAMETER_MSB: u8 = 101;
+
+/// The parameter number that deselects the current (N)RPN.
+const NULL_PARAMETER: u16 = 0x3FFF;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiValue {
+    /// A normalized value between 0.0 and 1.0.
+    Absolute(f32),
+    /// A normalized delta to add to the current value.
+    Relative(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodedMidi {
+    pub value: MidiValue,
+    pub pressed: bool,
+}
+
+/// Keeps the state that spans multiple messages from a single device, like
+/// 14-bit controller pairs and NRPN parameter selection.
+#[derive(Debug, Default)]
+pub struct MidiDecoder {
+    controllers: HashMap<(u8, u8), u8>,
+    nrpn: HashMap<u8, NrpnState>,
+    nrpn_event: Option<(u16, MidiValue)>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct NrpnState {
+    parameter_msb: Option<u8>,
+    parameter: Option<u16>,
+    value_msb: u8,
+}
+
+impl MidiDecoder {
+    /// Feeds the next message from the device into the decoder. This has to be
+    /// called for every message before decoding it with [MidiDecoder::decode].
+    pub fn update(&mut self, channel: u4, message: &midly::MidiMessage) {
+        self.nrpn_event = None;
+
+        let midly::MidiMessage::Controller { controller, value } = *message else {
+            return;
+        };
+        let (channel, controller, value) = (channel.as_u8(), controller.as_int(), value.as_int());
+
+        let state = self.nrpn.entry(channel).or_default();
+        let nrpn_value = match controller {
+            NRPN_PARAMETER_MSB => {
+                state.parameter_msb = Some(value);
+                state.parameter = None;
+                None
+            }
+            NRPN_PARAMETER_LSB => {
+                state.parameter = state
+                    .parameter_msb
+                    .map(|msb| (msb as u16) << 7 | value as u16)
+                    .filter(|parameter| *parameter != NULL_PARAMETER);
+                None
+            }
+            // Data entry now refers to an RPN, which we don't map.
+            RPN_PARAMETER_MSB | RPN_PARAMETER_LSB => {
+                *state = NrpnState::default();
+                None
+            }
+            DATA_ENTRY_MSB => {
+                state.value_msb = value;
+                // A new MSB resets the LSB.
+                Some(MidiValue::Absolute(from_14bit(value, 0)))
+            }
+            DATA_ENTRY_LSB => Some(MidiValue::Absolute(from_14bit(state.value_msb, value))),
+            DATA_INCREMENT => Some(MidiValue::Relative(RELATIVE_STEP)),
+            DATA_DECREMENT => Some(MidiValue::Relative(-RELATIVE_STEP)),
+            _ => None,
+        };
+        self.nrpn_event = state.parameter.zip(nrpn_value);
+
+        self.controllers.insert((channel, controller), value);
+    }
+
+    /// Decodes the last message passed to [MidiDecoder::update] for a mapping.
+    /// Returns `None` if the message does not apply to the mapping.
+    pub fn decode(
+        &self,
+        mapping: &MidiTriggerDefinition,
+        channel: u4,
+        message: &midly::MidiMessage,
+    ) -> Option<DecodedMidi> {
+        if !mapping.channel().contains(&channel) {
+            return None;
+        }
+
+        if let MidiMessage::Nrpn { parameter } = mapping.trigger() {
+            let (nrpn_parameter, value) = self.nrpn_event?;
+            if !parameter.contains(&nrpn_parameter) {
+                return None;
+            }
+            let pressed = match value {
+                MidiValue::Absolute(value) => value > 0.0,
+                MidiValue::Relative(_) => true,
+            };
+            return Some(DecodedMidi { value, pressed });
+        }
+
+        match *mapping.encoding() {
+            MidiValueEncoding::Absolute => {
+                if !message_matches(mapping.trigger(), message) {
+                    return None;
+                }
+                Some(DecodedMidi {
+                    value: MidiValue::Absolute(midi_value_as_f32(message)?),
+                    pressed: midi_pressed(message),
+                })
+            }
+            MidiValueEncoding::Absolute14Bit { lsb_controller } => {
+                let MidiMessage::ControlChange { controller: msb_controller, .. } =
+                    mapping.trigger()
+                else {
+                    return None;
+                };
+                let midly::MidiMessage::Controller { controller, value } = *message else {
+                    return None;
+                };
+
+                let value = if msb_controller.contains(&controller.into()) {
+                    // A new MSB resets the LSB.
+                    from_14bit(value.as_int(), 0)
+                } else if controller.as_int() == lsb_controller.as_u8() {
+                    let msb_controller = msb_controller.first()?.as_u8();
+                    let msb = *self.controllers.get(&(channel.as_u8(), msb_controller))?;
+                    from_14bit(msb, value.as_int())
+                } else {
+                    return None;
+                };
+
+                Some(DecodedMidi { value: MidiValue::Absolute(value), pressed: value > 0.0 })
+            }
+            encoding @ (MidiValueEncoding::RelativeTwosComplement
+            | MidiValueEncoding::RelativeSignMagnitude
+            | MidiValueEncoding::RelativeBinaryOffset) => {
+                if !message_matches(mapping.trigger(), message) {
+                    return None;
+                }
+
+                let data = midi_data_value(message)? as i16;
+                let ticks = match encoding {
+                    MidiValueEncoding::RelativeTwosComplement => {
+                        if data < 64 {
+                            data
+                        } else {
+                            data - 128
+                        }
+                    }
+                    MidiValueEncoding::RelativeSignMagnitude => {
+                        let magnitude = data & 0x3F;
+                        if data & 0x40 != 0 { -magnitude } else { magnitude }
+                    }
+                    _ => data - 64,
+                };
+
+                Some(DecodedMidi {
+                    value: MidiValue::Relative(ticks as f32 * RELATIVE_STEP),
+                    pressed: ticks != 0,
+                })
+            }
+        }
+    }
+}
+
+fn message_matches(mapping: &MidiMessage, message: &midly::MidiMessage) -> bool {
+    match (mapping, *message) {
+        (MidiMessage::NoteOff { note, velocity }, midly::MidiMessage::NoteOff { key, vel }) => {
+            note.contains(&key.into()) && velocity.contains(&vel.into())
+        }
+        (MidiMessage::NoteOn { note, velocity }, midly::MidiMessage::NoteOn { key, vel }) => {
+            note.contains(&key.into()) && velocity.contains(&vel.into())
+        }
+        (
+            MidiMessage::PolyphonicAftertouch { note, pressure },
+            midly::MidiMessage::Aftertouch { key, vel },
+        ) => note.contains(&key.into()) && pressure.contains(&vel.into()),
+        (
+            MidiMessage::ControlChange { controller: ctrl1, value: val1 },
+            midly::MidiMessage::Controller { controller: ctrl2, value: val2 },
+        ) => ctrl1.contains(&ctrl2.into()) && val1.contains(&val2.into()),
+        (
+            MidiMessage::ProgramChange { program: prog1 },
+            midly::MidiMessage::ProgramChange { program: prog2 },
+        ) => prog1.contains(&prog2.into()),
+        (
+            MidiMessage::ChannelAftertouch { pressure },
+            midly::MidiMessage::ChannelAftertouch { vel },
+        ) => pressure.contains(&vel.into()),
+        (MidiMessage::PitchBend { value }, midly::MidiMessage::PitchBend { bend }) => {
+            value.contains(&bend.as_int())
+        }
+        _ => false,
+    }
+}
+
+fn midi_pressed(message: &midly::MidiMessage) -> bool {
+    match *message {
+        // A NoteOn with zero velocity is the common running-status form of a NoteOff.
+        midly::MidiMessage::NoteOn { vel, .. } => vel.as_int() > 0,
+        midly::MidiMessage::NoteOff { .. } => false,
+        midly::MidiMessage::Controller { value, .. }
+        | midly::MidiMessage::Aftertouch { vel: value, .. }
+        | midly::MidiMessage::ChannelAftertouch { vel: value } => value.as_int() > 0,
+        midly::MidiMessage::ProgramChange { .. } => true,
+        midly::MidiMessage::PitchBend { .. } => true,
+    }
+}
+
+fn midi_data_value(message: &midly::MidiMessage) -> Option<u8> {
+    match *message {
+        midly::MidiMessage::NoteOff { vel, .. }
+        | midly::MidiMessage::NoteOn { vel, .. }
+        | midly::MidiMessage::Aftertouch { vel, .. }
+        | midly::MidiMessage::ChannelAftertouch { vel }
+        | midly::MidiMessage::Controller { value: vel, .. } => Some(vel.as_int()),
+        midly::MidiMessage::ProgramChange { program } => Some(program.as_int()),
+        midly::MidiMessage::PitchBend { .. } => None,
+    }
+}
+
+fn midi_value_as_f32(message: &midly::MidiMessage) -> Option<f32> {
+    let v = match *message {
+        midly::MidiMessage::PitchBend { bend } => {
+            let v = bend.as_int() as f32;
+            (v + 8192.0) / 16383.0
+        }
+        _ => midi_data_value(message)? as f32 / 127.0,
+    };
+
+    Some(v.clamp(0.0, 1.0))
+}
+
+fn from_14bit(msb: u8, lsb: u8) -> f32 {
+    ((msb as u16) << 7 | lsb as u16) as f32 / 16383.0
+}
+
+/// Builds the messages that move the control of a mapping to `value` (0.0..=1.0).
+pub fn feedback_messages(mapping: &MidiTriggerDefinition, value: f32) -> Option<Vec<Vec<u8>>> {
+    if mapping.encoding().is_relative() && mapping.feedback() == MidiFeedbackMode::Encoding {
+        return None;
+    }
+
+    let channel = mapping.channel().first()?.as_u8();
+    let value = value.clamp(0.0, 1.0);
+    let value_7bit = (value * 127.0).round() as u8;
+    let value_14bit = (value * 16383.0).round() as u16;
+    let (msb, lsb) = ((value_14bit >> 7) as u8, (value_14bit & 0x7F) as u8);
+
+    let cc = |controller: u8, value: u8| vec![0xB0 | channel, controller, value];
+
+    let messages = match (mapping.trigger(), mapping.encoding()) {
+        (
+            MidiMessage::ControlChange { controller, .. },
+            MidiValueEncoding::Absolute14Bit { lsb_controller },
+        ) => vec![cc(controller.first()?.as_u8(), msb), cc(lsb_controller.as_u8(), lsb)],
+        (MidiMessage::Nrpn { parameter }, _) => {
+            let parameter = parameter.first()?;
+            vec![
+                cc(NRPN_PARAMETER_MSB, (parameter >> 7) as u8 & 0x7F),
+                cc(NRPN_PARAMETER_LSB, parameter as u8 & 0x7F),
+                cc(DATA_ENTRY_MSB, msb),
+                cc(DATA_ENTRY_LSB, lsb),
+            ]
+        }
+        (MidiMessage::NoteOff { note, .. } | MidiMessage::NoteOn { note, .. }, _) => {
+            vec![vec![0x90 | channel, note.first()?.as_u8(), value_7bit]]
+        }
+        (MidiMessage::PolyphonicAftertouch { note, .. }, _) => {
+            vec![vec![0xA0 | channel, note.first()?.as_u8(), value_7bit]]
+        }
+        (MidiMessage::ControlChange { controller, .. }, _) => {
+            vec![cc(controller.first()?.as_u8(), value_7bit)]
+        }
+        (MidiMessage::ChannelAftertouch { .. }, _) => vec![vec![0xD0 | channel, value_7bit]],
+        (MidiMessage::PitchBend { .. }, _) => vec![vec![0xE0 | channel, lsb, msb]],
+        // A program change has no position that could be reflected.
+        (MidiMessage::ProgramChange { .. }, _) => return None,
+    };
+
+    Some(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn mapping(message: serde_json::Value, encoding: serde_json::Value) -> MidiTriggerDefinition {
+        serde_json::from_value(json!({
+            "device_name": "nanoKONTROL2",
+            "channel": 2,
+            "message": message,
+            "encoding": encoding,
+            "target": { "type": "Encoder", "encoder_ix": 0 },
+        }))
+        .unwrap()
+    }
+
+    fn cc(controller: u8, value: u8) -> midly::MidiMessage {
+        midly::MidiMessage::Controller { controller: controller.into(), value: value.into() }
+    }
+
+    fn decode(
+        decoder: &mut MidiDecoder,
+        mapping: &MidiTriggerDefinition,
+        message: midly::MidiMessage,
+    ) -> Option<MidiValue> {
+        let channel = u4::new(2).unwrap();
+        decoder.update(channel, &message);
+        decoder.decode(mapping, channel, &message).map(|decoded| decoded.value)
+    }
+
+    #[test]
+    fn decode_14bit_controller() {
+        let mapping = mapping(
+            json!({ "type": "ControlChange", "controller": 7, "value": { "from": 0, "to": 127 } }),
+            json!({ "type": "Absolute14Bit", "lsb_controller": 39 }),
+        );
+        let mut decoder = MidiDecoder::default();
+
+        assert_eq!(
+            decode(&mut decoder, &mapping, cc(7, 64)),
+            Some(MidiValue::Absolute(8192.0 / 16383.0))
+        );
+        assert_eq!(
+            decode(&mut decoder, &mapping, cc(39, 1)),
+            Some(MidiValue::Absolute(8193.0 / 16383.0))
+        );
+        assert_eq!(decode(&mut decoder, &mapping, cc(8, 1)), None);
+    }
+
+    #[test]
+    fn decode_nrpn() {
+        let mapping =
+            mapping(json!({ "type": "Nrpn", "parameter": 300 }), json!({ "type": "Absolute" }));
+        let mut decoder = MidiDecoder::default();
+
+        assert_eq!(decode(&mut decoder, &mapping, cc(99, 2)), None);
+        assert_eq!(decode(&mut decoder, &mapping, cc(98, 44)), None);
+        assert_eq!(
+            decode(&mut decoder, &mapping, cc(6, 127)),
+            Some(MidiValue::Absolute(16256.0 / 16383.0))
+        );
+        assert_eq!(decode(&mut decoder, &mapping, cc(38, 127)), Some(MidiValue::Absolute(1.0)));
+        assert_eq!(
+            decode(&mut decoder, &mapping, cc(96, 0)),
+            Some(MidiValue::Relative(RELATIVE_STEP))
+        );
+
+        // Another parameter.
+        decode(&mut decoder, &mapping, cc(98, 45));
+        assert_eq!(decode(&mut decoder, &mapping, cc(6, 0)), None);
+    }
+
+    #[test]
+    fn decode_relative_encodings() {
+        let message =
+            json!({ "type": "ControlChange", "controller": 16, "value": { "from": 0, "to": 127 } });
+        let cases = [
+            ("RelativeTwosComplement", [(1, 1), (127, -1), (65, -63)]),
+            ("RelativeSignMagnitude", [(1, 1), (65, -1), (127, -63)]),
+            ("RelativeBinaryOffset", [(65, 1), (63, -1), (64, 0)]),
+        ];
+
+        for (encoding, values) in cases {
+            let mapping = mapping(message.clone(), json!({ "type": encoding }));
+            let mut decoder = MidiDecoder::default();
+            for (value, ticks) in values {
+                assert_eq!(
+                    decode(&mut decoder, &mapping, cc(16, value)),
+                    Some(MidiValue::Relative(ticks as f32 * RELATIVE_STEP)),
+                    "{encoding} {value}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn feedback() {
+        let absolute = json!({ "type": "Absolute" });
+        let controller = json!({ "type": "ControlChange", "controller": { "from": 16, "to": 23 }, "value": { "from": 0, "to": 127 } });
+
+        let cc = mapping(controller.clone(), absolute.clone());
+        assert_eq!(feedback_messages(&cc, 1.0), Some(vec![vec![0xB2, 16, 127]]));
+        assert_eq!(feedback_messages(&cc, 0.5), Some(vec![vec![0xB2, 16, 64]]));
+
+        let cc_14bit =
+            mapping(controller, json!({ "type": "Absolute14Bit", "lsb_controller": 48 }));
+        assert_eq!(
+            feedback_messages(&cc_14bit, 1.0),
+            Some(vec![vec![0xB2, 16, 127], vec![0xB2, 48, 127]])
+        );
+
+        let nrpn = mapping(json!({ "type": "Nrpn", "parameter": 300 }), absolute.clone());
+        assert_eq!(
+            feedback_messages(&nrpn, 0.0),
+            Some(vec![vec![0xB2, 99, 2], vec![0xB2, 98, 44], vec![0xB2, 6, 0], vec![0xB2, 38, 0]])
+        );
+
+        let note =
+            mapping(json!({ "type": "NoteOff", "note": 41, "velocity": 0 }), absolute.clone());
+        assert_eq!(feedback_messages(&note, 0.0), Some(vec![vec![0x92, 41, 0]]));
+
+        let bend = mapping(
+            json!({ "type": "PitchBend", "value": { "from": -8192, "to": 8191 } }),
+            absolute.clone(),
+        );
+        assert_eq!(feedback_messages(&bend, 1.0), Some(vec![vec![0xE2, 0x7F, 0x7F]]));
+
+        let program = mapping(json!({ "type": "ProgramChange", "program": 1 }), absolute);
+        assert_eq!(feedback_messages(&program, 1.0), None);
+    }
+
+    #[test]
+    fn feedback_for_relative_encodings() {
+        let controller = json!({ "type": "ControlChange", "controller": 16, "value": 0 });
+        let encoder = mapping(controller, json!({ "type": "RelativeBinaryOffset" }));
+        assert_eq!(feedback_messages(&encoder, 0.5), None);
+
+        let mut encoder = serde_json::to_value(encoder).unwrap();
+        encoder["feedback"] = json!({ "type": "Absolute" });
+        let encoder = serde_json::from_value(encoder).unwrap();
+        assert_eq!(feedback_messages(&encoder, 0.5), Some(vec![vec![0xB2, 16, 64]]));
+    }
+}
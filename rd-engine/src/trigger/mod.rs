@@ -1,13 +1,42 @@
-use crate::object::{ExecutorButton, ExecutorId};
+use crate::{
+    cmd::Command,
+    gdtf::attr::AttributeName,
+    object::{ExecutorButton, ExecutorId},
+    value::ClampedValue,
+};
 
 mod agent;
 mod definition;
+mod midi;
+pub mod msc;
+pub mod osc;
 
 pub use agent::*;
 pub use definition::*;
 
 pub enum Trigger {
-    ExecutorMaster { executor_id: ExecutorId, value: f32 },
-    ExecutorButton { executor_id: ExecutorId, button: ExecutorButton, pressed: bool },
-    EncoderSetValue { encoder_ix: usize, value: f32 },
+    ExecutorMaster {
+        executor_id: ExecutorId,
+        value: f32,
+        relative: bool,
+    },
+    ExecutorButton {
+        executor_id: ExecutorId,
+        button: ExecutorButton,
+        pressed: bool,
+    },
+    EncoderSetValue {
+        encoder_ix: usize,
+        value: f32,
+        relative: bool,
+    },
+    ProgrammerAttribute {
+        attribute: AttributeName,
+        from: ClampedValue,
+        to: ClampedValue,
+        value: f32,
+        relative: bool,
+    },
+    Command(Command),
+    Msc(msc::MscCommand),
 }
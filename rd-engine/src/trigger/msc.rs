@@ -0,0 +1,339 @@
+//! Encoding and decoding of MIDI Show Control (MSC) SysEx messages.
+
+use std::num::NonZeroU32;
+
+use crate::{
+    cmd::Command,
+    object::{ExecutorContent, ExecutorId, Object, ObjectId, Objects, Slot},
+};
+
+/// The device ID every MSC receiver responds to.
+pub const ALL_CALL_DEVICE_ID: u8 = 0x7F;
+
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+const MSC_SUB_ID: u8 = 0x02;
+
+const COMMAND_FORMAT_LIGHTING: u8 = 0x01;
+const COMMAND_FORMAT_ALL_TYPES: u8 = 0x7F;
+
+const GO: u8 = 0x01;
+const STOP: u8 = 0x02;
+const RESUME: u8 = 0x03;
+const LOAD: u8 = 0x05;
+const SET: u8 = 0x06;
+const FIRE: u8 = 0x07;
+
+const MAX_14BIT: f32 = 16383.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MscMessage {
+    pub device_id: u8,
+    pub command_format: u8,
+    pub command: MscCommand,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MscCommand {
+    Go { cue: Option<MscCue> },
+    Stop { cue: Option<MscCue> },
+    Resume { cue: Option<MscCue> },
+    Load { cue: MscCue },
+    Set { control: u16, value: u16 },
+    Fire { macro_number: u8 },
+}
+
+/// A cue reference as sent over MSC. All fields are ASCII numbers like `"12.5"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MscCue {
+    pub number: String,
+    pub list: Option<String>,
+    pub path: Option<String>,
+}
+
+impl MscCue {
+    pub fn new(number: impl Into<String>, list: Option<String>) -> Self {
+        Self { number: number.into(), list, path: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum MscError {
+    #[error("not an MSC message")]
+    NotMsc,
+    #[error("message is truncated")]
+    Truncated,
+    #[error("unsupported command: {0:#04x}")]
+    UnsupportedCommand(u8),
+    #[error("invalid cue number: '{0}'")]
+    InvalidCueNumber(String),
+}
+
+impl MscMessage {
+    /// A lighting message addressed to `device_id`.
+    pub fn lighting(device_id: u8, command: MscCommand) -> Self {
+        Self { device_id, command_format: COMMAND_FORMAT_LIGHTING, command }
+    }
+
+    /// Whether the message is meant for a lighting console with the given device ID.
+    pub fn is_addressed_to(&self, device_id: u8) -> bool {
+        let is_lighting = matches!(self.command_format, COMMAND_FORMAT_LIGHTING..=0x0F)
+            || self.command_format == COMMAND_FORMAT_ALL_TYPES;
+        is_lighting && (self.device_id == device_id || self.device_id == ALL_CALL_DEVICE_ID)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes =
+            vec![SYSEX_START, UNIVERSAL_REAL_TIME, self.device_id, MSC_SUB_ID, self.command_format];
+
+        match &self.command {
+            MscCommand::Go { cue } => write_optional_cue(&mut bytes, GO, cue.as_ref()),
+            MscCommand::Stop { cue } => write_optional_cue(&mut bytes, STOP, cue.as_ref()),
+            MscCommand::Resume { cue } => write_optional_cue(&mut bytes, RESUME, cue.as_ref()),
+            MscCommand::Load { cue } => write_optional_cue(&mut bytes, LOAD, Some(cue)),
+            MscCommand::Set { control, value } => {
+                bytes.push(SET);
+                bytes.extend([
+                    (control & 0x7F) as u8,
+                    (control >> 7 & 0x7F) as u8,
+                    (value & 0x7F) as u8,
+                    (value >> 7 & 0x7F) as u8,
+                ]);
+            }
+            MscCommand::Fire { macro_number } => bytes.extend([FIRE, macro_number & 0x7F]),
+        }
+
+        bytes.push(SYSEX_END);
+        bytes
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, MscError> {
+        let data = data.strip_suffix(&[SYSEX_END]).unwrap_or(data);
+        let [SYSEX_START, UNIVERSAL_REAL_TIME, device_id, MSC_SUB_ID, command_format, rest @ ..] =
+            data
+        else {
+            return Err(MscError::NotMsc);
+        };
+        let [command, data @ ..] = rest else { return Err(MscError::Truncated) };
+
+        let command = match *command {
+            GO => MscCommand::Go { cue: read_cue(data)? },
+            STOP => MscCommand::Stop { cue: read_cue(data)? },
+            RESUME => MscCommand::Resume { cue: read_cue(data)? },
+            LOAD => MscCommand::Load { cue: read_cue(data)?.ok_or(MscError::Truncated)? },
+            SET => {
+                // A trailing time code is allowed, but ignored.
+                let [control_lsb, control_msb, value_lsb, value_msb, ..] = *data else {
+                    return Err(MscError::Truncated);
+                };
+                MscCommand::Set {
+                    control: u16::from(control_msb) << 7 | u16::from(control_lsb),
+                    value: u16::from(value_msb) << 7 | u16::from(value_lsb),
+                }
+            }
+            FIRE => {
+                let [macro_number, ..] = *data else { return Err(MscError::Truncated) };
+                MscCommand::Fire { macro_number }
+            }
+            other => return Err(MscError::UnsupportedCommand(other)),
+        };
+
+        Ok(Self { device_id: *device_id, command_format: *command_format, command })
+    }
+}
+
+fn write_optional_cue(bytes: &mut Vec<u8>, command: u8, cue: Option<&MscCue>) {
+    bytes.push(command);
+    let Some(cue) = cue else { return };
+
+    bytes.extend(cue.number.as_bytes());
+    for field in [&cue.list, &cue.path] {
+        let Some(field) = field else { break };
+        bytes.push(0);
+        bytes.extend(field.as_bytes());
+    }
+}
+
+fn read_cue(data: &[u8]) -> Result<Option<MscCue>, MscError> {
+    let mut fields = data.split(|&b| b == 0).map(|field| {
+        let field = std::str::from_utf8(field).unwrap_or_default();
+        if field.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+            Ok(field.to_string())
+        } else {
+            Err(MscError::InvalidCueNumber(field.to_string()))
+        }
+    });
+
+    let number = fields.next().transpose()?.filter(|number| !number.is_empty());
+    let Some(number) = number else { return Ok(None) };
+    let list = fields.next().transpose()?.filter(|list| !list.is_empty());
+    let path = fields.next().transpose()?.filter(|path| !path.is_empty());
+    Ok(Some(MscCue { number, list, path }))
+}
+
+/// Resolves an incoming MSC command into the commands that drive sequence playback.
+///
+/// Cue lists refer to sequences by slot and cue numbers to their position in
+/// the sequence (starting at 1). Commands without a cue list apply to all
+/// sequence executors on `executor_page`. SET controls map to the executor
+/// masters on `executor_page`, starting at control 0.
+pub(crate) fn playback_commands(
+    command: &MscCommand,
+    objects: &Objects,
+    executor_page: Option<ObjectId>,
+) -> Result<Vec<Command>, MscError> {
+    let executor_page =
+        executor_page.or_else(|| objects.executor_pages().all().first().map(|page| page.id()));
+
+    let commands = match command {
+        MscCommand::Go { cue } => {
+            let cue_index = cue.as_ref().map(cue_index).transpose()?;
+            sequence_executors(cue.as_ref(), objects, executor_page)
+                .map(|executor_id| Command::ExecutorCueGo { executor_id, cue_index })
+                .collect()
+        }
+        MscCommand::Stop { cue } => sequence_executors(cue.as_ref(), objects, executor_page)
+            .map(|executor_id| Command::ExecutorSetEnabled { executor_id, value: false })
+            .collect(),
+        MscCommand::Resume { cue } => sequence_executors(cue.as_ref(), objects, executor_page)
+            .map(|executor_id| Command::ExecutorSetEnabled { executor_id, value: true })
+            .collect(),
+        MscCommand::Load { cue } => {
+            let cue_index = cue_index(cue)?;
+            sequence_executors(Some(cue), objects, executor_page)
+                .map(|executor_id| Command::ExecutorCueLoad { executor_id, cue_index })
+                .collect()
+        }
+        MscCommand::Set { control, value } => {
+            let slot = u32::from(*control) + 1;
+            let (Some(page), Some(slot)) = (executor_page, NonZeroU32::new(slot)) else {
+                return Ok(Vec::new());
+            };
+            let executor_id = ExecutorId::new(page, Slot::new(slot));
+            if objects.executor(&executor_id).is_err() {
+                return Ok(Vec::new());
+            }
+            vec![Command::ExecutorSetMaster { executor_id, value: f32::from(*value) / MAX_14BIT }]
+        }
+        // Macros are resolved by the triggers agent.
+        MscCommand::Fire { .. } => Vec::new(),
+    };
+
+    Ok(commands)
+}
+
+/// The MSC cue reference for the current cue of a sequence executor.
+pub(crate) fn executor_cue(executor_id: &ExecutorId, objects: &Objects) -> Option<MscCue> {
+    let executor = objects.executor(executor_id).ok()?;
+    let Some(ExecutorContent::Sequence(sc)) = executor.content() else {
+        return None;
+    };
+    let sequence = objects.sequences().get_by_object_id(&sc.sequence()).ok()?;
+    Some(MscCue::new((sc.cue_index() + 1).to_string(), Some(sequence.slot().to_string())))
+}
+
+fn cue_index(cue: &MscCue) -> Result<usize, MscError> {
+    // Cues are only addressed by position, so point cues can't be resolved.
+    match cue.number.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(number - 1),
+        _ => Err(MscError::InvalidCueNumber(cue.number.clone())),
+    }
+}
+
+fn sequence_executors<'a>(
+    cue: Option<&MscCue>,
+    objects: &'a Objects,
+    executor_page: Option<ObjectId>,
+) -> impl Iterator<Item = ExecutorId> + 'a {
+    // A cue list that doesn't resolve to a sequence matches no executors.
+    let sequence = cue.and_then(|cue| cue.list.as_deref()).map(|list| {
+        let slot = list.parse::<u32>().ok().and_then(NonZeroU32::new)?;
+        objects.sequences().get_by_slot(&Slot::new(slot)).ok().map(|sequence| sequence.id())
+    });
+
+    objects
+        .executor_pages()
+        .all()
+        .iter()
+        .filter(move |page| sequence.is_some() || Some(page.id()) == executor_page)
+        .flat_map(move |page| {
+            page.executors().iter().enumerate().filter_map(move |(ix, executor)| {
+                let Some(ExecutorContent::Sequence(sc)) = executor.content() else {
+                    return None;
+                };
+                if let Some(sequence) = sequence
+                    && sequence != Some(sc.sequence())
+                {
+                    return None;
+                }
+                let slot = NonZeroU32::new(ix as u32 + 1)?;
+                Some(ExecutorId::new(page.id(), Slot::new(slot)))
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_go_with_cue_list() {
+        let bytes = [0xF0, 0x7F, 0x01, 0x02, 0x01, 0x01, b'1', b'2', 0x00, b'3', 0xF7];
+        let message = MscMessage::decode(&bytes).unwrap();
+        assert_eq!(
+            message,
+            MscMessage {
+                device_id: 1,
+                command_format: COMMAND_FORMAT_LIGHTING,
+                command: MscCommand::Go { cue: Some(MscCue::new("12", Some("3".to_string()))) },
+            }
+        );
+        assert!(message.is_addressed_to(1));
+        assert!(!message.is_addressed_to(2));
+        assert_eq!(message.encode(), bytes);
+    }
+
+    #[test]
+    fn decode_without_cue() {
+        let bytes = [0xF0, 0x7F, 0x7F, 0x02, 0x7F, 0x02, 0xF7];
+        let message = MscMessage::decode(&bytes).unwrap();
+        assert_eq!(message.command, MscCommand::Stop { cue: None });
+        assert!(message.is_addressed_to(5));
+    }
+
+    #[test]
+    fn decode_set_and_fire() {
+        let set = [0xF0, 0x7F, 0x01, 0x02, 0x01, 0x06, 0x02, 0x00, 0x7F, 0x7F, 0xF7];
+        assert_eq!(
+            MscMessage::decode(&set).unwrap().command,
+            MscCommand::Set { control: 2, value: 16383 }
+        );
+
+        let fire = [0xF0, 0x7F, 0x01, 0x02, 0x01, 0x07, 0x05, 0xF7];
+        assert_eq!(
+            MscMessage::decode(&fire).unwrap().command,
+            MscCommand::Fire { macro_number: 5 }
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(
+            MscMessage::decode(&[0xF0, 0x7E, 0x01, 0x06, 0x01, 0xF7]),
+            Err(MscError::NotMsc)
+        );
+        assert_eq!(
+            MscMessage::decode(&[0xF0, 0x7F, 0x01, 0x02, 0x01, 0x05, 0xF7]),
+            Err(MscError::Truncated)
+        );
+        assert_eq!(
+            MscMessage::decode(&[0xF0, 0x7F, 0x01, 0x02, 0x01, 0x10, 0xF7]),
+            Err(MscError::UnsupportedCommand(0x10))
+        );
+        assert!(matches!(
+            MscMessage::decode(&[0xF0, 0x7F, 0x01, 0x02, 0x01, 0x01, b'x', 0xF7]),
+            Err(MscError::InvalidCueNumber(_))
+        ));
+    }
+}
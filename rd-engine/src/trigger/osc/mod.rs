@@ -0,0 +1,7 @@
+//! Open Sound Control input and feedback.
+
+mod packet;
+mod server;
+
+pub use packet::*;
+pub use server::*;
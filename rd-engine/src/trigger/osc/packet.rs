@@ -0,0 +1,326 @@
+//! Encoding and decoding of OSC 1.0 packets.
+
+/// A single argument of an [OscMessage].
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Blob(Vec<u8>),
+    Long(i64),
+    Double(f64),
+    Bool(bool),
+    Nil,
+    Impulse,
+}
+
+impl OscArg {
+    /// Interprets the argument as a normalized value, if it carries one.
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            OscArg::Int(v) => Some(v as f32),
+            OscArg::Float(v) => Some(v),
+            OscArg::Long(v) => Some(v as f32),
+            OscArg::Double(v) => Some(v as f32),
+            OscArg::Bool(v) => Some(if v { 1.0 } else { 0.0 }),
+            OscArg::String(_) | OscArg::Blob(_) | OscArg::Nil | OscArg::Impulse => None,
+        }
+    }
+
+    fn type_tag(&self) -> char {
+        match self {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::String(_) => 's',
+            OscArg::Blob(_) => 'b',
+            OscArg::Long(_) => 'h',
+            OscArg::Double(_) => 'd',
+            OscArg::Bool(true) => 'T',
+            OscArg::Bool(false) => 'F',
+            OscArg::Nil => 'N',
+            OscArg::Impulse => 'I',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    pub fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self { address: address.into(), args }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_string(&mut bytes, &self.address);
+
+        let type_tags: String =
+            std::iter::once(',').chain(self.args.iter().map(OscArg::type_tag)).collect();
+        write_string(&mut bytes, &type_tags);
+
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(v) => bytes.extend(v.to_be_bytes()),
+                OscArg::Float(v) => bytes.extend(v.to_be_bytes()),
+                OscArg::String(v) => write_string(&mut bytes, v),
+                OscArg::Blob(v) => {
+                    bytes.extend((v.len() as i32).to_be_bytes());
+                    bytes.extend(v);
+                    pad(&mut bytes);
+                }
+                OscArg::Long(v) => bytes.extend(v.to_be_bytes()),
+                OscArg::Double(v) => bytes.extend(v.to_be_bytes()),
+                OscArg::Bool(_) | OscArg::Nil | OscArg::Impulse => {}
+            }
+        }
+
+        bytes
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, OscError> {
+        let mut offset = 0;
+
+        let address = read_string(data, &mut offset)?;
+        if !address.starts_with('/') {
+            return Err(OscError::InvalidAddress(address));
+        }
+
+        // Very old implementations omit the type tag string for messages without arguments.
+        if offset >= data.len() {
+            return Ok(Self { address, args: Vec::new() });
+        }
+
+        let type_tags = read_string(data, &mut offset)?;
+        let Some(type_tags) = type_tags.strip_prefix(',') else {
+            return Err(OscError::MissingTypeTags);
+        };
+
+        let mut args = Vec::with_capacity(type_tags.len());
+        for tag in type_tags.chars() {
+            let arg = match tag {
+                'i' => OscArg::Int(i32::from_be_bytes(read_bytes(data, &mut offset)?)),
+                'f' => OscArg::Float(f32::from_be_bytes(read_bytes(data, &mut offset)?)),
+                's' | 'S' => OscArg::String(read_string(data, &mut offset)?),
+                'b' => {
+                    let len = i32::from_be_bytes(read_bytes(data, &mut offset)?);
+                    let len = usize::try_from(len).map_err(|_| OscError::Truncated)?;
+                    let blob = data.get(offset..offset + len).ok_or(OscError::Truncated)?;
+                    offset += padded_len(len);
+                    OscArg::Blob(blob.to_vec())
+                }
+                'h' => OscArg::Long(i64::from_be_bytes(read_bytes(data, &mut offset)?)),
+                'd' => OscArg::Double(f64::from_be_bytes(read_bytes(data, &mut offset)?)),
+                'T' => OscArg::Bool(true),
+                'F' => OscArg::Bool(false),
+                'N' => OscArg::Nil,
+                'I' => OscArg::Impulse,
+                other => return Err(OscError::UnsupportedTypeTag(other)),
+            };
+            args.push(arg);
+        }
+
+        Ok(Self { address, args })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum OscError {
+    #[error("packet is truncated")]
+    Truncated,
+    #[error("string is not valid UTF-8")]
+    InvalidString,
+    #[error("invalid address: '{0}'")]
+    InvalidAddress(String),
+    #[error("missing type tag string")]
+    MissingTypeTags,
+    #[error("unsupported type tag: '{0}'")]
+    UnsupportedTypeTag(char),
+}
+
+/// Decodes a packet into its messages, flattening any (nested) bundles.
+///
+/// Bundle time tags are ignored; all messages are treated as immediate.
+pub fn decode_packet(data: &[u8]) -> Result<Vec<OscMessage>, OscError> {
+    let mut messages = Vec::new();
+    decode_packet_into(data, &mut messages)?;
+    Ok(messages)
+}
+
+fn decode_packet_into(data: &[u8], messages: &mut Vec<OscMessage>) -> Result<(), OscError> {
+    const BUNDLE_HEADER: &[u8] = b"#bundle\0";
+
+    if !data.starts_with(BUNDLE_HEADER) {
+        messages.push(OscMessage::decode(data)?);
+        return Ok(());
+    }
+
+    // Skip the header and the 8 byte time tag.
+    let mut offset = BUNDLE_HEADER.len() + 8;
+    if data.len() < offset {
+        return Err(OscError::Truncated);
+    }
+
+    while offset < data.len() {
+        let size = i32::from_be_bytes(read_bytes(data, &mut offset)?);
+        let size = usize::try_from(size).map_err(|_| OscError::Truncated)?;
+        let element = data.get(offset..offset + size).ok_or(OscError::Truncated)?;
+        decode_packet_into(element, messages)?;
+        offset += size;
+    }
+
+    Ok(())
+}
+
+/// Checks whether an OSC address pattern matches an address.
+///
+/// Supports `?`, `*`, `[abc]`, `[a-z]`, `[!abc]` and `{foo,bar}`. None of
+/// these match across a `/`.
+pub fn pattern_matches(pattern: &str, address: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let address = address.chars().collect::<Vec<_>>();
+    matches(&pattern, &address)
+}
+
+fn matches(pattern: &[char], address: &[char]) -> bool {
+    match pattern.first() {
+        None => address.is_empty(),
+        Some('*') => (0..=address.len())
+            .take_while(|&len| len == 0 || address[len - 1] != '/')
+            .any(|len| matches(&pattern[1..], &address[len..])),
+        Some('?') => {
+            address.first().is_some_and(|&c| c != '/') && matches(&pattern[1..], &address[1..])
+        }
+        Some('[') => {
+            let Some(end) = pattern.iter().position(|&c| c == ']') else { return false };
+            let Some(&c) = address.first() else { return false };
+            if c == '/' {
+                return false;
+            }
+
+            let (negate, set) = match &pattern[1..end] {
+                ['!', set @ ..] => (true, set),
+                set => (false, set),
+            };
+
+            let mut found = false;
+            let mut ix = 0;
+            while ix < set.len() {
+                if ix + 2 < set.len() && set[ix + 1] == '-' {
+                    found |= set[ix] <= c && c <= set[ix + 2];
+                    ix += 3;
+                } else {
+                    found |= set[ix] == c;
+                    ix += 1;
+                }
+            }
+
+            found != negate && matches(&pattern[end + 1..], &address[1..])
+        }
+        Some('{') => {
+            let Some(end) = pattern.iter().position(|&c| c == '}') else { return false };
+            pattern[1..end].split(|&c| c == ',').any(|alternative| {
+                address.starts_with(alternative)
+                    && matches(&pattern[end + 1..], &address[alternative.len()..])
+            })
+        }
+        Some(&c) => address.first() == Some(&c) && matches(&pattern[1..], &address[1..]),
+    }
+}
+
+fn padded_len(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn pad(bytes: &mut Vec<u8>) {
+    bytes.resize(padded_len(bytes.len()), 0);
+}
+
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend(s.as_bytes());
+    bytes.push(0);
+    pad(bytes);
+}
+
+fn read_string(data: &[u8], offset: &mut usize) -> Result<String, OscError> {
+    let rest = data.get(*offset..).ok_or(OscError::Truncated)?;
+    let len = rest.iter().position(|&b| b == 0).ok_or(OscError::Truncated)?;
+    let s = std::str::from_utf8(&rest[..len]).map_err(|_| OscError::InvalidString)?;
+    *offset += padded_len(len + 1);
+    Ok(s.to_string())
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: &mut usize) -> Result<[u8; N], OscError> {
+    let bytes = data.get(*offset..*offset + N).ok_or(OscError::Truncated)?;
+    *offset += N;
+    Ok(bytes.try_into().expect("slice should have length N"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_round_trip() {
+        let message = OscMessage::new(
+            "/exec/1/fader",
+            vec![
+                OscArg::Float(0.5),
+                OscArg::Int(-3),
+                OscArg::String("Cue 1".to_string()),
+                OscArg::Blob(vec![1, 2, 3]),
+                OscArg::Bool(true),
+                OscArg::Nil,
+            ],
+        );
+
+        let bytes = message.encode();
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(OscMessage::decode(&bytes), Ok(message));
+    }
+
+    #[test]
+    fn encode_matches_spec_example() {
+        let bytes = OscMessage::new("/oscillator/4/frequency", vec![OscArg::Float(440.0)]).encode();
+        assert_eq!(bytes, b"/oscillator/4/frequency\0,f\0\0\x43\xdc\x00\x00".to_vec(),);
+    }
+
+    #[test]
+    fn decode_bundle() {
+        let first = OscMessage::new("/a", vec![OscArg::Int(1)]);
+        let second = OscMessage::new("/b", vec![]);
+
+        let mut bytes = b"#bundle\0".to_vec();
+        bytes.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        for message in [&first, &second] {
+            let encoded = message.encode();
+            bytes.extend((encoded.len() as i32).to_be_bytes());
+            bytes.extend(encoded);
+        }
+
+        assert_eq!(decode_packet(&bytes), Ok(vec![first, second]));
+    }
+
+    #[test]
+    fn decode_truncated() {
+        let bytes = OscMessage::new("/a", vec![OscArg::Float(1.0)]).encode();
+        assert_eq!(OscMessage::decode(&bytes[..bytes.len() - 2]), Err(OscError::Truncated));
+        assert!(OscMessage::decode(b"abc\0").is_err());
+    }
+
+    #[test]
+    fn address_patterns() {
+        assert!(pattern_matches("/exec/1/fader", "/exec/1/fader"));
+        assert!(!pattern_matches("/exec/1/fader", "/exec/2/fader"));
+        assert!(pattern_matches("/exec/?/fader", "/exec/2/fader"));
+        assert!(pattern_matches("/exec/*/fader", "/exec/12/fader"));
+        assert!(!pattern_matches("/exec/*", "/exec/1/fader"));
+        assert!(pattern_matches("/exec/[1-3]/go", "/exec/2/go"));
+        assert!(!pattern_matches("/exec/[!1-3]/go", "/exec/2/go"));
+        assert!(pattern_matches("/exec/{go,flash}", "/exec/flash"));
+        assert!(!pattern_matches("/exec/{go,flash}", "/exec/stop"));
+    }
+}
@@ -0,0 +1,172 @@
+use std::io;
+use std::net::UdpSocket;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::Context as _;
+
+use crate::{
+    event::Event,
+    object::{ExecutorId, ObjectId, ObjectKind, Objects},
+    trigger::{
+        OscDefinition, OscFeedbackSource, OscTriggerDefinition, Trigger,
+        osc::{OscArg, OscMessage, decode_packet, pattern_matches},
+    },
+};
+
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+const MAX_PACKET_SIZE: usize = 65_507;
+
+/// Receives OSC messages and turns them into [Trigger]s, and sends executor
+/// state back to the configured feedback addresses.
+pub struct OscServer {
+    definition: OscDefinition,
+    socket: UdpSocket,
+
+    thread_handle: Option<JoinHandle<()>>,
+    thread_running: Arc<AtomicBool>,
+}
+
+impl OscServer {
+    pub fn start(
+        definition: OscDefinition,
+        trigger_tx: flume::Sender<Trigger>,
+    ) -> anyhow::Result<Self> {
+        let socket = UdpSocket::bind(definition.listen_address()).with_context(|| {
+            format!("Failed to bind OSC server to {}", definition.listen_address())
+        })?;
+        socket.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        // Feedback is sent from the listening socket, so controllers that reply
+        // to the sender's port receive it on the same port they send to.
+        let recv_socket = socket.try_clone()?;
+        let triggers = definition.triggers().to_vec();
+
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = thread::Builder::new()
+            .name("rd_osc".to_string())
+            .spawn({
+                let running = running.clone();
+                move || {
+                    let mut buffer = vec![0; MAX_PACKET_SIZE];
+                    while running.load(Ordering::SeqCst) {
+                        match recv_socket.recv_from(&mut buffer) {
+                            Ok((len, _)) => match decode_packet(&buffer[..len]) {
+                                Ok(messages) => {
+                                    for message in messages {
+                                        handle_message(&message, &triggers, &trigger_tx);
+                                    }
+                                }
+                                Err(err) => log::warn!("Received invalid OSC packet: {err}"),
+                            },
+                            Err(err)
+                                if matches!(
+                                    err.kind(),
+                                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                                ) => {}
+                            Err(err) => {
+                                log::error!("Failed to receive OSC packet: {err}");
+                                thread::sleep(READ_TIMEOUT);
+                            }
+                        }
+                    }
+                }
+            })
+            .context("Failed to spawn OSC server thread")?;
+
+        log::info!("OSC server listening on {}", definition.listen_address());
+
+        Ok(Self { definition, socket, thread_handle: Some(handle), thread_running: running })
+    }
+
+    pub fn stop(&mut self) {
+        if self.thread_handle.is_some() {
+            self.thread_running.store(false, Ordering::SeqCst);
+            if let Some(handle) = self.thread_handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    pub fn handle_event(&self, event: &Event, objects: &Objects, selected_page: Option<ObjectId>) {
+        let (Event::ObjectChanged { object_kind: ObjectKind::ExecutorPage, object_id }
+        | Event::ExecutorPageSelected { object_id }) = event
+        else {
+            return;
+        };
+
+        for feedback in self.definition.feedback() {
+            let executor_id = feedback.source().executor_id().on_selected_page(selected_page);
+            if executor_id.page != *object_id {
+                continue;
+            }
+
+            let Some(arg) = feedback_value(feedback.source(), &executor_id, objects) else {
+                continue;
+            };
+            let bytes = OscMessage::new(feedback.address(), vec![arg]).encode();
+            for address in self.definition.feedback_addresses() {
+                if let Err(err) = self.socket.send_to(&bytes, address) {
+                    log::warn!("Failed to send OSC feedback to {address}: {err}");
+                }
+            }
+        }
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn feedback_value(
+    source: &OscFeedbackSource,
+    executor_id: &ExecutorId,
+    objects: &Objects,
+) -> Option<OscArg> {
+    let executor = objects.executor(executor_id).ok()?;
+    let arg = match source {
+        OscFeedbackSource::ExecutorMaster { .. } => OscArg::Float(executor.master()),
+        OscFeedbackSource::ExecutorEnabled { .. } => {
+            OscArg::Float(if executor.enabled() { 1.0 } else { 0.0 })
+        }
+        OscFeedbackSource::ExecutorCueName { .. } => OscArg::String(
+            objects.current_cue(executor).map(|cue| cue.name().to_string()).unwrap_or_default(),
+        ),
+    };
+    Some(arg)
+}
+
+fn handle_message(
+    message: &OscMessage,
+    triggers: &[OscTriggerDefinition],
+    trigger_tx: &flume::Sender<Trigger>,
+) {
+    log::debug!("Received OSC message: {:?}", message);
+
+    let value = message.args.first().and_then(OscArg::as_f32);
+
+    for mapping in triggers.iter().filter(|t| pattern_matches(t.address(), &message.address)) {
+        let target = mapping.target();
+        let triggers = match value {
+            Some(value) => vec![target.to_trigger(value, false, value > 0.0)],
+            None if target.takes_value() => {
+                log::warn!("Mapped OSC message did not carry a value usable as f32: {:?}", message);
+                continue;
+            }
+            // Messages without a value (e.g. from cue software) act as a full button press.
+            None => vec![target.to_trigger(1.0, false, true), target.to_trigger(0.0, false, false)],
+        };
+
+        for trigger in triggers.into_iter().flatten() {
+            if let Err(err) = trigger_tx.send(trigger) {
+                log::error!("Failed to send trigger: {}", err);
+            }
+        }
+    }
+}
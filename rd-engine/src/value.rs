@@ -296,6 +296,22 @@ impl ClampedValue {
 
         addresses.iter().copied().zip(bytes.into_iter().map(dmx::Value::from)).collect()
     }
+
+    /// Converts the values at the addresses of a channel back into a value, the inverse of
+    /// [`ClampedValue::to_address_values`].
+    pub fn from_address_values(values: &[dmx::Value]) -> Option<Self> {
+        if !(1..=4).contains(&values.len()) {
+            log::warn!(
+                "cannot get value from DMX channel for fixture: unsupported address length {}",
+                values.len()
+            );
+            return None;
+        }
+
+        let raw = values.iter().fold(0_u64, |raw, value| raw << 8 | u64::from(value.as_u8()));
+        let max = (1_u64 << (8 * values.len())) - 1;
+        Some(Self::new((raw as f64 / max as f64) as f32))
+    }
 }
 
 impl fmt::Display for ClampedValue {
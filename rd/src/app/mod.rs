@@ -2,7 +2,12 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use gpui::{Context, Entity, FocusHandle, Hsla, ReadGlobal, Window, div, prelude::*, px, size};
-use rd_engine::{Engine, EngineHandle, Project, cmd::Command, event::Event};
+use rd_engine::{
+    Engine, EngineHandle, Project,
+    cmd::Command,
+    event::Event,
+    remote::{RemoteConfig, RemoteServer},
+};
 use rd_ui::{
     ActiveTheme, Button, Icon, IconSize, IconVariant, TITLE_BAR_HEIGHT, TITLE_BAR_RIGHT_PADDING,
     h_flex,
@@ -83,7 +88,7 @@ pub(crate) mod action {
     }
 }
 
-pub fn run(showfile_path: Option<PathBuf>) -> Result<()> {
+pub fn run(showfile_path: Option<PathBuf>, remote: Option<RemoteConfig>) -> Result<()> {
     log::debug!("Starting Radiant...");
     rd_ui::build_simple_app()
         .window_title(if cfg!(debug_assertions) { "Radiant [debug-mode]" } else { "Radiant" })
@@ -114,7 +119,14 @@ pub fn run(showfile_path: Option<PathBuf>) -> Result<()> {
 
             keymap::default_keymap().apply(cx);
 
-            crate::engine::init(EngineHandle::new(rd_engine), cx);
+            let engine_handle = EngineHandle::new(rd_engine);
+            let remote_server = remote.and_then(|config| {
+                RemoteServer::start(config, engine_handle.clone())
+                    .inspect_err(|err| log::error!("Could not start remote server: {err}"))
+                    .ok()
+            });
+
+            crate::engine::init(engine_handle, remote_server, cx);
 
             log::info!("Started Radiant");
 
@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
 use gpui::{App, AppContext, Entity, EventEmitter, Global, ReadGlobal, Subscription};
-use rd_engine::{EngineHandle, EngineSnapshot, cmd::Command, event::Event};
+use rd_engine::{EngineHandle, EngineSnapshot, cmd::Command, event::Event, remote::RemoteServer};
 
-pub(crate) fn init(handle: EngineHandle, cx: &mut App) {
-    let engine_global = EngineGlobal::new(handle, cx);
+pub(crate) fn init(handle: EngineHandle, remote_server: Option<RemoteServer>, cx: &mut App) {
+    let engine_global = EngineGlobal::new(handle, remote_server, cx);
     cx.set_global(engine_global);
 }
 
@@ -68,10 +68,11 @@ impl EventEmitter<Event> for EngineEventBus {}
 struct EngineGlobal {
     handle: EngineHandle,
     event_buffer: Entity<EngineEventBus>,
+    _remote_server: Option<RemoteServer>,
 }
 
 impl EngineGlobal {
-    pub fn new(handle: EngineHandle, cx: &mut App) -> Self {
+    pub fn new(handle: EngineHandle, remote_server: Option<RemoteServer>, cx: &mut App) -> Self {
         let event_buffer = cx.new(|_| EngineEventBus);
 
         cx.spawn({
@@ -90,7 +91,7 @@ impl EngineGlobal {
         })
         .detach();
 
-        Self { handle, event_buffer }
+        Self { handle, event_buffer, _remote_server: remote_server }
     }
 }
 
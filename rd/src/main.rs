@@ -1,6 +1,7 @@
-use std::path::PathBuf;
+use std::{net::SocketAddr, path::PathBuf};
 
 use clap::Parser;
+use rd_engine::remote::RemoteConfig;
 
 mod app;
 mod engine;
@@ -10,6 +11,14 @@ mod engine;
 struct Args {
     /// Path to the showfile to load.
     showfile_path: Option<PathBuf>,
+
+    /// Address to serve the remote-control API on, e.g. `0.0.0.0:9000`.
+    #[arg(long, requires = "remote_token")]
+    remote_address: Option<SocketAddr>,
+
+    /// Token remote-control clients have to authenticate with.
+    #[arg(long)]
+    remote_token: Option<String>,
 }
 
 fn init_logger() {
@@ -24,7 +33,12 @@ fn main() -> anyhow::Result<()> {
 
     let args = Args::parse();
 
-    app::run(args.showfile_path)?;
+    let remote = args
+        .remote_address
+        .zip(args.remote_token)
+        .map(|(address, token)| RemoteConfig { address, token });
+
+    app::run(args.showfile_path, remote)?;
 
     Ok(())
 }